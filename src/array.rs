@@ -8,6 +8,24 @@ pub fn all_equals<T: PartialEq>(buffer: &[T], value: T) -> bool {
     buffer.iter().all(|x| *x == value)
 }
 
+/// Return true if value is NaN; this is only possible for floating point types
+#[inline]
+#[allow(clippy::eq_op)]
+pub fn is_nan<T: PartialEq>(value: T) -> bool {
+    value != value
+}
+
+/// Return true if value equals nodata or is NaN
+#[inline]
+pub fn is_nodata<T: PartialEq + Copy>(value: T, nodata: T) -> bool {
+    value == nodata || is_nan(value)
+}
+
+/// Return true if all values in the slice equal nodata or are NaN
+pub fn all_nodata<T: PartialEq + Copy>(buffer: &[T], nodata: T) -> bool {
+    buffer.iter().all(|x| is_nodata(*x, nodata))
+}
+
 pub fn equals<T: PartialEq>(left: &[T], right: &[T]) -> bool {
     left.len() == right.len() && left.iter().zip(right.iter()).all(|(l, r)| l == r)
 }
@@ -63,6 +81,34 @@ pub fn shift<T: Copy>(
     }
 }
 
+/// Set all values outside windows of the buffer to fill
+///
+/// # Parameters
+/// * buffer: slice of values in row-major order
+/// * size: shape of the buffer (width, height)
+/// * windows: (col_start, row_start, col_stop, row_stop) of values to keep;
+///            stop values are exclusive
+/// * fill: fill value for values outside all windows
+pub fn fill_outside<T: Copy>(
+    buffer: &mut [T],
+    size: (usize, usize),
+    windows: &[(usize, usize, usize, usize)],
+    fill: T,
+) {
+    for row in 0..size.1 {
+        for col in 0..size.0 {
+            let inside = windows
+                .iter()
+                .any(|&(col_start, row_start, col_stop, row_stop)| {
+                    row >= row_start && row < row_stop && col >= col_start && col < col_stop
+                });
+            if !inside {
+                buffer[row * size.0 + col] = fill;
+            }
+        }
+    }
+}
+
 pub fn print_2d<T: PartialEq + Ord + std::fmt::Debug>(
     buffer: &[T],
     size: (usize, usize),
@@ -117,6 +163,16 @@ mod tests {
         assert!(!all_equals(&[0u64, 1u64], 0u64));
     }
 
+    #[test]
+    fn test_all_nodata() {
+        assert!(all_nodata(&[0u8, 0u8], 0u8));
+        assert!(!all_nodata(&[0u8, 1u8], 0u8));
+        assert!(all_nodata(&[-9999f32, f32::NAN], -9999f32));
+        assert!(all_nodata(&[f32::NAN, f32::NAN], f32::NAN));
+        assert!(all_nodata(&[f64::NAN, f64::NAN], f64::NAN));
+        assert!(!all_nodata(&[f64::NAN, 0f64], f64::NAN));
+    }
+
     #[test]
     fn test_equals() {
         assert!(equals(&[0i8, 1i8], &[0i8, 1i8]));
@@ -207,6 +263,36 @@ mod tests {
         assert!(equals(&buffer, &expected));
     }
 
+    #[rstest]
+    #[case((0, 0, 4, 3), [
+        1, 1, 1, 1,
+        1, 1, 1, 1,
+        1, 1, 1, 1
+    ])]
+    #[case((1, 1, 3, 2), [
+        0, 0, 0, 0,
+        0, 1, 1, 0,
+        0, 0, 0, 0
+    ])]
+    #[case((2, 0, 2, 3), [
+        0, 0, 0, 0,
+        0, 0, 0, 0,
+        0, 0, 0, 0
+    ])]
+    fn test_fill_outside(#[case] window: (usize, usize, usize, usize), #[case] expected: [u8; 12]) {
+        let mut buffer = [1u8; 12];
+        fill_outside(&mut buffer, (4, 3), &[window], 0);
+        assert!(equals(&buffer, &expected));
+    }
+
+    #[test]
+    fn test_fill_outside_windows() {
+        let mut buffer = [1u8; 12];
+        fill_outside(&mut buffer, (4, 3), &[(0, 0, 1, 3), (3, 1, 4, 2)], 0);
+        let expected = [1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0, 0];
+        assert!(equals(&buffer, &expected));
+    }
+
     #[rstest]
     #[case([0u8,0u8,0u8,0u8], BTreeMap::from([(0u8, 4u64)]))]
     #[case([0u8,0u8,1u8,0u8], BTreeMap::from([(0u8, 3u64), (1u8, 1u64)]))]
@@ -1,7 +1,352 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Bounds {
     pub xmin: f64,
     pub ymin: f64,
     pub xmax: f64,
     pub ymax: f64,
 }
+
+impl Bounds {
+    /// Parse bounds from "xmin,ymin,xmax,ymax"; xmin may be greater than xmax
+    pub fn parse(value: &str) -> Result<Bounds, String> {
+        let values = value
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("invalid bounds: {}", value))?;
+
+        // xmin is greater than xmax for geographic bounds that cross the
+        // antimeridian
+        match *values.as_slice() {
+            [xmin, ymin, xmax, ymax] if xmin != xmax && ymin < ymax => Ok(Bounds {
+                xmin,
+                ymin,
+                xmax,
+                ymax,
+            }),
+            [_, _, _, _] => Err(String::from(
+                "bounds must have xmin not equal to xmax and ymin less than ymax",
+            )),
+            _ => Err(String::from("bounds must be xmin,ymin,xmax,ymax")),
+        }
+    }
+
+    /// Return the intersection with other bounds, or None if they do not
+    /// overlap
+    pub fn intersection(&self, other: &Bounds) -> Option<Bounds> {
+        let bounds = Bounds {
+            xmin: self.xmin.max(other.xmin),
+            ymin: self.ymin.max(other.ymin),
+            xmax: self.xmax.min(other.xmax),
+            ymax: self.ymax.min(other.ymax),
+        };
+
+        if bounds.xmin < bounds.xmax && bounds.ymin < bounds.ymax {
+            Some(bounds)
+        } else {
+            None
+        }
+    }
+
+    /// Return the intersections of each of parts with each of others, in order
+    /// of parts; parts that do not overlap any of others are omitted
+    pub fn intersections(parts: &[Bounds], others: &[Bounds]) -> Vec<Bounds> {
+        parts
+            .iter()
+            .flat_map(|part| others.iter().filter_map(|other| part.intersection(other)))
+            .collect()
+    }
+
+    /// Return true if geographic bounds cross the antimeridian, either as
+    /// xmin greater than xmax or as longitudes beyond -180 or 180
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.xmin > self.xmax || self.xmin < -180. || self.xmax > 180.
+    }
+
+    /// Split geographic bounds that cross the antimeridian into the part east
+    /// of it (up to 180) and the part west of it (from -180)
+    pub fn split_antimeridian(&self) -> Vec<Bounds> {
+        if !self.crosses_antimeridian() {
+            return vec![*self];
+        }
+
+        // normalize longitudes beyond -180 or 180
+        let (xmin, xmax) = match (self.xmin, self.xmax) {
+            (xmin, xmax) if xmax - xmin >= 360. => (-180., 180.),
+            (xmin, xmax) if xmax > 180. => (xmin, xmax - 360.),
+            (xmin, xmax) if xmin < -180. => (xmin + 360., xmax),
+            (xmin, xmax) => (xmin, xmax),
+        };
+
+        if xmin <= xmax {
+            return vec![Bounds {
+                xmin,
+                xmax,
+                ..*self
+            }];
+        }
+
+        vec![
+            Bounds {
+                xmin,
+                xmax: 180.,
+                ..*self
+            },
+            Bounds {
+                xmin: -180.,
+                xmax,
+                ..*self
+            },
+        ]
+    }
+
+    /// Join geographic bounds split by split_antimeridian(); bounds that cross
+    /// the antimeridian have xmin greater than xmax
+    pub fn join_antimeridian(parts: &[Bounds]) -> Bounds {
+        match parts {
+            [east, west] => Bounds {
+                xmin: east.xmin,
+                ymin: east.ymin.min(west.ymin),
+                xmax: west.xmax,
+                ymax: east.ymax.max(west.ymax),
+            },
+            _ => parts[0],
+        }
+    }
+
+    /// Return geographic bounds that cross the antimeridian (xmin greater than
+    /// xmax) expanded to the full range of longitudes, which clients that do
+    /// not support such bounds can interpret; other bounds are unchanged
+    pub fn expand_antimeridian(&self) -> Bounds {
+        if self.xmin <= self.xmax {
+            return *self;
+        }
+
+        Bounds {
+            xmin: -180.,
+            xmax: 180.,
+            ..*self
+        }
+    }
+
+    /// Return bounds shifted by width in x if they do not overlap other in x
+    /// but the shifted bounds do, e.g., to wrap bounds around the antimeridian
+    /// where width is the width of the world; otherwise bounds are unchanged
+    pub fn wrap_x(&self, other: &Bounds, width: f64) -> Bounds {
+        let overlaps = |b: &Bounds| b.xmin < other.xmax && b.xmax > other.xmin;
+        if overlaps(self) {
+            return *self;
+        }
+
+        [width, -width]
+            .iter()
+            .map(|shift| Bounds {
+                xmin: self.xmin + shift,
+                xmax: self.xmax + shift,
+                ..*self
+            })
+            .find(overlaps)
+            .unwrap_or(*self)
+    }
+
+    /// Return smallest bounds that contain all bounds
+    pub fn union(parts: &[Bounds]) -> Bounds {
+        Bounds {
+            xmin: parts.iter().map(|b| b.xmin).fold(f64::MAX, f64::min),
+            ymin: parts.iter().map(|b| b.ymin).fold(f64::MAX, f64::min),
+            xmax: parts.iter().map(|b| b.xmax).fold(f64::MIN, f64::max),
+            ymax: parts.iter().map(|b| b.ymax).fold(f64::MIN, f64::max),
+        }
+    }
+
+    /// Return center (x, y) of geographic bounds, which may cross the
+    /// antimeridian
+    pub fn center(&self) -> (f64, f64) {
+        let mut x = (self.xmin + self.xmax) / 2.;
+        if self.xmin > self.xmax {
+            x += 180.;
+            if x > 180. {
+                x -= 360.;
+            }
+        }
+        (x, (self.ymin + self.ymax) / 2.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::approx_eq_bounds;
+    use rstest::rstest;
+
+    #[test]
+    fn parse() {
+        let bounds = Bounds::parse("-100.5, 20,-90,30.25").unwrap();
+        assert!(approx_eq_bounds(
+            &bounds,
+            &Bounds {
+                xmin: -100.5,
+                ymin: 20.,
+                xmax: -90.,
+                ymax: 30.25
+            },
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn parse_antimeridian() {
+        let bounds = Bounds::parse("175,-10,-175,10").unwrap();
+        assert_eq!(
+            (bounds.xmin, bounds.ymin, bounds.xmax, bounds.ymax),
+            (175., -10., -175., 10.)
+        );
+        assert!(bounds.crosses_antimeridian());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("1,2,3")]
+    #[case("1,2,3,a")]
+    #[case("1,2,1,4")]
+    #[case("1,2,3,2")]
+    fn parse_invalid(#[case] value: &str) {
+        assert!(Bounds::parse(value).is_err());
+    }
+
+    #[test]
+    fn intersection() {
+        let left = Bounds {
+            xmin: 0.,
+            ymin: 0.,
+            xmax: 10.,
+            ymax: 10.,
+        };
+        let right = Bounds {
+            xmin: 5.,
+            ymin: -5.,
+            xmax: 15.,
+            ymax: 5.,
+        };
+        let actual = left.intersection(&right).unwrap();
+        assert!(approx_eq_bounds(
+            &actual,
+            &Bounds {
+                xmin: 5.,
+                ymin: 0.,
+                xmax: 10.,
+                ymax: 5.
+            },
+            1e-9
+        ));
+
+        let outside = Bounds {
+            xmin: 10.,
+            ymin: 0.,
+            xmax: 20.,
+            ymax: 10.,
+        };
+        assert!(left.intersection(&outside).is_none());
+    }
+
+    #[test]
+    fn intersections() {
+        // dataset and clip both cross the antimeridian
+        let areas = Bounds {
+            xmin: 170.,
+            ymin: -5.,
+            xmax: -170.,
+            ymax: 5.,
+        }
+        .split_antimeridian();
+        let clip = Bounds::parse("175,-10,185,10")
+            .unwrap()
+            .split_antimeridian();
+
+        let actual = Bounds::intersections(&areas, &clip);
+        assert_eq!(
+            actual
+                .iter()
+                .map(|b| (b.xmin, b.ymin, b.xmax, b.ymax))
+                .collect::<Vec<_>>(),
+            [(175., -5., 180., 5.), (-180., -5., -175., 5.)]
+        );
+        assert!(approx_eq_bounds(
+            &Bounds::join_antimeridian(&actual),
+            &Bounds {
+                xmin: 175.,
+                ymin: -5.,
+                xmax: -175.,
+                ymax: 5.
+            },
+            1e-9
+        ));
+    }
+
+    #[rstest]
+    #[case(Bounds{xmin: -10., ymin: -5., xmax: 10., ymax: 5.}, vec![(-10., 10.)])]
+    #[case(Bounds{xmin: 170., ymin: -5., xmax: -170., ymax: 5.}, vec![(170., 180.), (-180., -170.)])]
+    #[case(Bounds{xmin: 170., ymin: -5., xmax: 190., ymax: 5.}, vec![(170., 180.), (-180., -170.)])]
+    #[case(Bounds{xmin: -190., ymin: -5., xmax: -170., ymax: 5.}, vec![(170., 180.), (-180., -170.)])]
+    #[case(Bounds{xmin: -200., ymin: -5., xmax: 200., ymax: 5.}, vec![(-180., 180.)])]
+    fn split_antimeridian(#[case] bounds: Bounds, #[case] expected: Vec<(f64, f64)>) {
+        let parts = bounds.split_antimeridian();
+        assert_eq!(
+            parts.iter().map(|b| (b.xmin, b.xmax)).collect::<Vec<_>>(),
+            expected
+        );
+        assert!(parts.iter().all(|b| b.ymin == -5. && b.ymax == 5.));
+    }
+
+    #[test]
+    fn join_antimeridian() {
+        let bounds = Bounds {
+            xmin: 170.,
+            ymin: -5.,
+            xmax: -170.,
+            ymax: 5.,
+        };
+        let joined = Bounds::join_antimeridian(&bounds.split_antimeridian());
+        assert!(approx_eq_bounds(&joined, &bounds, 1e-9));
+    }
+
+    #[rstest]
+    #[case(Bounds{xmin: -10., ymin: -5., xmax: 10., ymax: 5.}, (-10., 10.))]
+    #[case(Bounds{xmin: 170., ymin: -5., xmax: -170., ymax: 5.}, (-180., 180.))]
+    fn expand_antimeridian(#[case] bounds: Bounds, #[case] expected: (f64, f64)) {
+        let expanded = bounds.expand_antimeridian();
+        assert_eq!((expanded.xmin, expanded.xmax), expected);
+        assert_eq!((expanded.ymin, expanded.ymax), (-5., 5.));
+    }
+
+    #[rstest]
+    // overlaps
+    #[case(Bounds{xmin: 170., ymin: 0., xmax: 180., ymax: 10.}, (170., 180.))]
+    // west of antimeridian is wrapped east of it
+    #[case(Bounds{xmin: -180., ymin: 0., xmax: -170., ymax: 10.}, (180., 190.))]
+    // east of other is wrapped west of it
+    #[case(Bounds{xmin: 525., ymin: 0., xmax: 535., ymax: 10.}, (165., 175.))]
+    // far from other even when wrapped
+    #[case(Bounds{xmin: 0., ymin: 0., xmax: 10., ymax: 10.}, (0., 10.))]
+    #[case(Bounds{xmin: 200., ymin: 0., xmax: 210., ymax: 10.}, (200., 210.))]
+    fn wrap_x(#[case] bounds: Bounds, #[case] expected: (f64, f64)) {
+        let other = Bounds {
+            xmin: 165.,
+            ymin: -10.,
+            xmax: 195.,
+            ymax: 10.,
+        };
+        let wrapped = bounds.wrap_x(&other, 360.);
+        assert_eq!((wrapped.xmin, wrapped.xmax), expected);
+        assert_eq!((wrapped.ymin, wrapped.ymax), (0., 10.));
+    }
+
+    #[rstest]
+    #[case(Bounds{xmin: -10., ymin: -5., xmax: 20., ymax: 5.}, (5., 0.))]
+    #[case(Bounds{xmin: 170., ymin: -5., xmax: -170., ymax: 5.}, (180., 0.))]
+    #[case(Bounds{xmin: 160., ymin: -5., xmax: -170., ymax: 5.}, (175., 0.))]
+    #[case(Bounds{xmin: 170., ymin: -5., xmax: -160., ymax: 5.}, (-175., 0.))]
+    fn center(#[case] bounds: Bounds, #[case] expected: (f64, f64)) {
+        assert_eq!(bounds.center(), expected);
+    }
+}
@@ -0,0 +1,372 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+use gdal::vector::{Geometry, LayerAccess};
+use gdal::Dataset as GDALDataset;
+
+use crate::bounds::Bounds;
+use crate::dataset::tms_spatial_ref;
+use crate::tms::TileMatrixSet;
+
+/// Maximum number of edges in each chunk of a ring; chunks with bounds that
+/// do not overlap a tile are skipped when testing that tile
+const CHUNK_SIZE: usize = 64;
+
+type Point = (f64, f64);
+
+/// Consecutive points of a polygon ring and their bounds
+#[derive(Debug)]
+struct Chunk {
+    points: Vec<Point>,
+    bounds: Bounds,
+}
+
+/// How tile bounds overlap a cutline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlap {
+    Disjoint,
+    /// Bounds are entirely within the cutline
+    Within,
+    Partial,
+}
+
+/// Polygons outside of which pixels are set to NODATA.  Polygons are read
+/// using OGR but stored as rings of points, since OGR geometries cannot be
+/// shared between workers; rings are combined using the even-odd rule.
+#[derive(Debug, Clone)]
+pub struct Cutline {
+    chunks: Arc<Vec<Chunk>>,
+    /// Bounds in the CRS of the tile matrix set
+    pub bounds: Bounds,
+}
+
+impl Cutline {
+    /// Create cutline from polygon rings, which must not overlap each other
+    pub fn new(rings: &[Vec<Point>]) -> Result<Cutline, Box<dyn Error>> {
+        let mut chunks: Vec<Chunk> = Vec::new();
+        for ring in rings.iter().filter(|r| r.len() >= 3) {
+            let mut ring = ring.to_vec();
+            if ring.first() != ring.last() {
+                ring.push(ring[0]);
+            }
+
+            for start in (0..ring.len() - 1).step_by(CHUNK_SIZE) {
+                let points = ring[start..=(start + CHUNK_SIZE).min(ring.len() - 1)].to_vec();
+                let bounds = points_bounds(&points);
+                chunks.push(Chunk { points, bounds });
+            }
+        }
+
+        if chunks.is_empty() {
+            return Err("cutline does not contain any polygons".into());
+        }
+
+        let bounds = Bounds {
+            xmin: chunks
+                .iter()
+                .map(|c| c.bounds.xmin)
+                .fold(f64::MAX, f64::min),
+            ymin: chunks
+                .iter()
+                .map(|c| c.bounds.ymin)
+                .fold(f64::MAX, f64::min),
+            xmax: chunks
+                .iter()
+                .map(|c| c.bounds.xmax)
+                .fold(f64::MIN, f64::max),
+            ymax: chunks
+                .iter()
+                .map(|c| c.bounds.ymax)
+                .fold(f64::MIN, f64::max),
+        };
+
+        Ok(Cutline {
+            chunks: Arc::new(chunks),
+            bounds,
+        })
+    }
+
+    /// Read polygons of all features in all layers of a vector file (e.g.,
+    /// GeoJSON, Shapefile, GeoPackage) and transform them to the CRS of the
+    /// tile matrix set; overlapping polygons are merged
+    pub fn read(path: &Path, tms: &TileMatrixSet) -> Result<Cutline, Box<dyn Error>> {
+        let dataset = GDALDataset::open(path)?;
+        let sp_ref = tms_spatial_ref(tms)?;
+
+        let mut union: Option<Geometry> = None;
+        for mut layer in dataset.layers() {
+            if layer.spatial_ref().is_none() {
+                return Err("cutline must have a spatial reference".into());
+            }
+
+            for feature in layer.features() {
+                let Some(geometry) = feature.geometry() else {
+                    continue;
+                };
+                let geometry = geometry.transform_to(&sp_ref)?;
+                union = Some(match union {
+                    Some(u) => u
+                        .union(&geometry)
+                        .ok_or("could not merge cutline polygons")?,
+                    None => geometry,
+                });
+            }
+        }
+
+        let mut rings: Vec<Vec<Point>> = Vec::new();
+        if let Some(geometry) = union {
+            collect_rings(&geometry, &mut rings);
+        }
+
+        Cutline::new(&rings)
+    }
+
+    /// Chunks that may have edges crossed by a horizontal line within the
+    /// y range of bounds, at or to the right of the left edge of bounds
+    fn chunks_right_of<'a>(&'a self, bounds: &'a Bounds) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks.iter().filter(|c| {
+            c.bounds.ymax >= bounds.ymin
+                && c.bounds.ymin <= bounds.ymax
+                && c.bounds.xmax >= bounds.xmin
+        })
+    }
+
+    /// Return true if point is inside cutline
+    fn contains_point(&self, (x, y): Point) -> bool {
+        let point_bounds = Bounds {
+            xmin: x,
+            ymin: y,
+            xmax: x,
+            ymax: y,
+        };
+        let mut count = 0;
+        for chunk in self.chunks_right_of(&point_bounds) {
+            for edge in chunk.points.windows(2) {
+                if let Some(crossing) = crossing(edge[0], edge[1], y)
+                    && crossing > x
+                {
+                    count += 1;
+                }
+            }
+        }
+        count % 2 == 1
+    }
+
+    /// Determine how bounds overlap the cutline
+    pub fn relate(&self, bounds: &Bounds) -> Overlap {
+        if self.bounds.xmax < bounds.xmin
+            || self.bounds.xmin > bounds.xmax
+            || self.bounds.ymax < bounds.ymin
+            || self.bounds.ymin > bounds.ymax
+        {
+            return Overlap::Disjoint;
+        }
+
+        for chunk in self.chunks_right_of(bounds) {
+            if chunk.bounds.xmin > bounds.xmax {
+                continue;
+            }
+            for edge in chunk.points.windows(2) {
+                if segment_intersects(edge[0], edge[1], bounds) {
+                    return Overlap::Partial;
+                }
+            }
+        }
+
+        // no edges cross bounds, so bounds are either entirely inside or
+        // outside the cutline
+        let center = (
+            (bounds.xmin + bounds.xmax) / 2.,
+            (bounds.ymin + bounds.ymax) / 2.,
+        );
+        match self.contains_point(center) {
+            true => Overlap::Within,
+            false => Overlap::Disjoint,
+        }
+    }
+
+    /// Set mask to true for pixels of a square tile with centers inside the
+    /// cutline, and false otherwise
+    pub fn mask(&self, tile_bounds: &Bounds, tile_size: usize, mask: &mut [bool]) {
+        let size = tile_size as f64;
+        let xres = (tile_bounds.xmax - tile_bounds.xmin) / size;
+        let yres = (tile_bounds.ymax - tile_bounds.ymin) / size;
+        let index = |x: f64| ((x - tile_bounds.xmin) / xres - 0.5).ceil().clamp(0., size) as usize;
+
+        let edges: Vec<(Point, Point)> = self
+            .chunks_right_of(tile_bounds)
+            .flat_map(|c| c.points.windows(2).map(|e| (e[0], e[1])))
+            .collect();
+
+        let mut crossings: Vec<f64> = Vec::new();
+        for row in 0..tile_size {
+            let y = tile_bounds.ymax - (row as f64 + 0.5) * yres;
+            crossings.clear();
+            crossings.extend(edges.iter().filter_map(|&(a, b)| crossing(a, b, y)));
+            crossings.sort_unstable_by(f64::total_cmp);
+
+            // pixels are inside if an odd number of crossings are to their right
+            let row_mask = &mut mask[row * tile_size..(row + 1) * tile_size];
+            row_mask.fill(false);
+            let count = crossings.len();
+            let mut start = 0;
+            for (i, &x) in crossings.iter().enumerate() {
+                let stop = index(x);
+                if (count - i) % 2 == 1 {
+                    row_mask[start..stop.max(start)].fill(true);
+                }
+                start = stop.max(start);
+            }
+        }
+    }
+}
+
+/// Return x coordinate where edge crosses horizontal line at y, if any; the
+/// lower end of each edge is included and upper end excluded so that shared
+/// vertices are only counted once
+fn crossing(a: Point, b: Point, y: f64) -> Option<f64> {
+    if (a.1 <= y) == (b.1 <= y) {
+        return None;
+    }
+    Some(a.0 + (y - a.1) * (b.0 - a.0) / (b.1 - a.1))
+}
+
+/// Return true if line segment from a to b intersects bounds, using
+/// Liang-Barsky clipping
+fn segment_intersects(a: Point, b: Point, bounds: &Bounds) -> bool {
+    let dx = b.0 - a.0;
+    let dy = b.1 - a.1;
+    let mut t0 = 0f64;
+    let mut t1 = 1f64;
+
+    for (p, q) in [
+        (-dx, a.0 - bounds.xmin),
+        (dx, bounds.xmax - a.0),
+        (-dy, a.1 - bounds.ymin),
+        (dy, bounds.ymax - a.1),
+    ] {
+        if p == 0. {
+            if q < 0. {
+                return false;
+            }
+        } else {
+            let t = q / p;
+            if p < 0. {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn points_bounds(points: &[Point]) -> Bounds {
+    Bounds {
+        xmin: points.iter().map(|p| p.0).fold(f64::MAX, f64::min),
+        ymin: points.iter().map(|p| p.1).fold(f64::MAX, f64::min),
+        xmax: points.iter().map(|p| p.0).fold(f64::MIN, f64::max),
+        ymax: points.iter().map(|p| p.1).fold(f64::MIN, f64::max),
+    }
+}
+
+/// Add rings of all polygons within geometry; other types of geometries are
+/// ignored
+fn collect_rings(geometry: &Geometry, rings: &mut Vec<Vec<Point>>) {
+    match geometry.geometry_name().as_str() {
+        "POLYGON" => {
+            for i in 0..geometry.geometry_count() {
+                let ring = geometry.get_geometry(i);
+                rings.push(
+                    ring.get_point_vec()
+                        .iter()
+                        .map(|&(x, y, _)| (x, y))
+                        .collect(),
+                );
+            }
+        }
+        "MULTIPOLYGON" | "GEOMETRYCOLLECTION" => {
+            for i in 0..geometry.geometry_count() {
+                collect_rings(&geometry.get_geometry(i), rings);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Square from 0 to 10 with a hole from 4 to 6
+    fn cutline() -> Cutline {
+        Cutline::new(&[
+            vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.)],
+            vec![(4., 4.), (4., 6.), (6., 6.), (6., 4.), (4., 4.)],
+        ])
+        .unwrap()
+    }
+
+    fn bounds(xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Bounds {
+        Bounds {
+            xmin,
+            ymin,
+            xmax,
+            ymax,
+        }
+    }
+
+    #[rstest]
+    #[case(bounds(20., 20., 30., 30.), Overlap::Disjoint)]
+    #[case(bounds(4.5, 4.5, 5.5, 5.5), Overlap::Disjoint)]
+    #[case(bounds(1., 1., 3., 3.), Overlap::Within)]
+    #[case(bounds(-1., -1., 1., 1.), Overlap::Partial)]
+    #[case(bounds(3., 3., 7., 7.), Overlap::Partial)]
+    #[case(bounds(-5., -5., 15., 15.), Overlap::Partial)]
+    fn relate(#[case] bounds: Bounds, #[case] expected: Overlap) {
+        assert_eq!(cutline().relate(&bounds), expected);
+    }
+
+    #[test]
+    fn mask() {
+        let mut mask = vec![false; 16];
+        cutline().mask(&bounds(-3., -1., 7., 9.), 4, &mut mask);
+
+        #[rustfmt::skip]
+        let expected = [
+            false, true, true, true,
+            false, true, true, false,
+            false, true, true, true,
+            false, true, true, true,
+        ];
+        assert_eq!(mask, expected);
+    }
+
+    #[test]
+    fn new_chunks() {
+        let ring: Vec<Point> = (0..200)
+            .map(|i| {
+                let angle = i as f64 / 200. * std::f64::consts::TAU;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        let cutline = Cutline::new(&[ring]).unwrap();
+        assert_eq!(cutline.chunks.len(), 4);
+        assert_eq!(cutline.chunks.last().unwrap().points.len(), 9);
+        assert_eq!(
+            cutline.relate(&bounds(-0.1, -0.1, 0.1, 0.1)),
+            Overlap::Within
+        );
+    }
+
+    #[test]
+    fn new_empty() {
+        assert!(Cutline::new(&[vec![(0., 0.), (1., 1.)]]).is_err());
+    }
+}
@@ -1,24 +1,32 @@
 #![allow(unused)]
 
-use libc::c_double;
+use libc::{c_double, c_int};
 use std::error::Error;
 use std::ffi::CString;
 use std::path::PathBuf;
 
 use gdal::cpl::CslStringList;
 use gdal::raster::{Buffer, GdalType, RasterBand, RasterCreationOptions, ResampleAlg};
-use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use gdal::spatial_ref::{AxisMappingStrategy, CoordTransform, SpatialRef};
 use gdal::{Dataset as GDALDataset, DatasetOptions, DriverManager};
 use gdal_sys::{GDALAutoCreateWarpedVRT, GDALCreateWarpOptions, GDALDatasetH, GDALResampleAlg};
 
 use crate::affine::Affine;
-use crate::array::{all_equals, set_all, shift};
+use crate::array::{all_nodata, fill_outside, set_all, shift};
 use crate::bounds::Bounds;
-use crate::tileid::TileID;
+use crate::cutline::{Cutline, Overlap};
+use crate::tms::TileMatrixSet;
 use crate::window::Window;
 
 pub struct Dataset {
     ds: GDALDataset,
+    /// Bounds outside all of which pixels are set to NODATA when reading tiles
+    clip: Vec<Bounds>,
+    /// Polygons outside of which pixels are set to NODATA when reading tiles
+    cutline: Option<Cutline>,
+    /// Width of the world in units of the CRS; tiles outside the extent of
+    /// the dataset are read from the other side of the antimeridian
+    world_width: Option<f64>,
 }
 
 impl Dataset {
@@ -31,6 +39,9 @@ impl Dataset {
 
         Ok(Dataset {
             ds: GDALDataset::open_ex(path, options)?,
+            clip: Vec::new(),
+            cutline: None,
+            world_width: None,
         })
     }
 
@@ -47,6 +58,12 @@ impl Dataset {
         })
     }
 
+    /// Size (x, y) of a pixel in units of the dataset's CRS
+    pub fn resolution(&self) -> Result<(f64, f64), Box<dyn Error>> {
+        let transform = self.ds.geo_transform()?;
+        Ok(Affine::from_gdal(&transform).resolution())
+    }
+
     pub fn transform_bounds(&self, crs: &SpatialRef) -> Result<Bounds, Box<dyn Error>> {
         let bounds = self.bounds()?;
         let src_crs = self.ds.spatial_ref()?;
@@ -70,8 +87,13 @@ impl Dataset {
         self.transform_bounds(&SpatialRef::from_epsg(3857)?)
     }
 
+    /// Bounds in the CRS of the tile matrix set, with x / easting first
+    pub fn tms_bounds(&self, tms: &TileMatrixSet) -> Result<Bounds, Box<dyn Error>> {
+        self.transform_bounds(&tms_spatial_ref(tms)?)
+    }
+
     // TODO: migrate to georust/gdal
-    fn warped_vrt(&self, sp_ref: &SpatialRef) -> Result<Dataset, Box<dyn Error>> {
+    fn warped_vrt(&self, sp_ref: &SpatialRef, add_alpha: bool) -> Result<Dataset, Box<dyn Error>> {
         let src_wkt = CString::new(self.ds.spatial_ref()?.to_wkt()?)?;
         let target_wkt = CString::new(sp_ref.to_wkt()?)?;
 
@@ -86,6 +108,12 @@ impl Dataset {
             (*options).papszWarpOptions = str_opts.as_ptr();
         }
 
+        // alpha band is added after all bands of the dataset, and is 0 outside
+        // the footprint of the dataset and for NODATA pixels
+        if add_alpha {
+            unsafe { (*options).nDstAlphaBand = (self.band_count() + 1) as c_int };
+        }
+
         let vrt: GDALDatasetH = unsafe {
             GDALAutoCreateWarpedVRT(
                 self.ds.c_dataset(),
@@ -102,30 +130,79 @@ impl Dataset {
         }
 
         let gdal_dataset = unsafe { GDALDataset::from_c_dataset(vrt) };
-        Ok(Dataset { ds: gdal_dataset })
+        Ok(Dataset {
+            ds: gdal_dataset,
+            clip: Vec::new(),
+            cutline: None,
+            world_width: None,
+        })
     }
 
     pub fn mercator_vrt(&self) -> Result<Dataset, Box<dyn Error>> {
-        self.warped_vrt(&SpatialRef::from_epsg(3857)?)
+        self.warped_vrt(&SpatialRef::from_epsg(3857)?, false)
+    }
+
+    /// Create a warped VRT in the CRS of the tile matrix set; pixels outside
+    /// all clip bounds or the cutline (in the same CRS) are read as NODATA
+    pub fn tms_vrt(
+        &self,
+        tms: &TileMatrixSet,
+        clip: &[Bounds],
+        cutline: Option<&Cutline>,
+    ) -> Result<Dataset, Box<dyn Error>> {
+        let mut vrt = self.warped_vrt(&tms_spatial_ref(tms)?, false)?;
+        vrt.clip = clip.to_vec();
+        vrt.cutline = cutline.cloned();
+        vrt.world_width = tms.world_width();
+        Ok(vrt)
+    }
+
+    /// Create a warped VRT like tms_vrt(), with an alpha band added after the
+    /// bands of the dataset that marks which pixels have data.  Unlike the
+    /// mask band of a warped VRT, this excludes pixels outside the footprint of
+    /// the dataset even if it does not have a NODATA value.
+    pub fn tms_alpha_vrt(
+        &self,
+        tms: &TileMatrixSet,
+        clip: &[Bounds],
+        cutline: Option<&Cutline>,
+    ) -> Result<Dataset, Box<dyn Error>> {
+        let mut vrt = self.warped_vrt(&tms_spatial_ref(tms)?, true)?;
+        vrt.clip = clip.to_vec();
+        vrt.cutline = cutline.cloned();
+        vrt.world_width = tms.world_width();
+        Ok(vrt)
+    }
+
+    pub fn band_count(&self) -> usize {
+        self.ds.raster_count()
     }
 
     pub fn band(&self, band_index: usize) -> Result<RasterBand<'_>, Box<dyn Error>> {
         Ok(self.ds.rasterband(band_index)?)
     }
 
-    /// Read tile data into buffer
+    /// Read data within tile bounds, in coordinates of the dataset, into buffer
     ///
     /// # Returns
     /// Some(bool) if read is successful; value of bool indicates if tile has data
     /// None if there is an error
-    pub fn read_tile<T: Copy + PartialEq + Ord + GdalType + std::fmt::Debug>(
+    pub fn read_tile<T: Copy + PartialEq + GdalType + std::fmt::Debug>(
         &self,
         band: &RasterBand,
-        tile_id: TileID,
+        tile_bounds: &Bounds,
         tile_size: u16,
         buffer: &mut [T],
         nodata: T,
     ) -> Result<bool, Box<dyn Error>> {
+        let overlap = match &self.cutline {
+            Some(cutline) => cutline.relate(tile_bounds),
+            None => Overlap::Within,
+        };
+        if overlap == Overlap::Disjoint {
+            return Ok(false);
+        }
+
         let tile_size = tile_size as usize;
         let size = tile_size as f64;
 
@@ -135,18 +212,25 @@ impl Dataset {
         let vrt_transform = Affine::from_gdal(&self.ds.geo_transform()?);
         let vrt_bounds = self.bounds()?;
 
-        let tile_bounds = tile_id.mercator_bounds();
-        let window = Window::from_bounds(&vrt_transform, &tile_bounds);
+        // the extent of the VRT of a dataset that crosses the antimeridian may
+        // extend beyond it, so tiles on the other side are read from there;
+        // clip bounds and cutline apply to the original tile bounds
+        let read_bounds = match self.world_width {
+            Some(width) => tile_bounds.wrap_x(&vrt_bounds, width),
+            None => *tile_bounds,
+        };
+
+        let window = Window::from_bounds(&vrt_transform, &read_bounds);
         let tile_transform = window
             .transform(&vrt_transform)
             .scale(window.width / size, window.height / size);
 
         let (xres, yres) = tile_transform.resolution();
 
-        let left = (((vrt_bounds.xmin - tile_bounds.xmin) / xres).round()).max(0.);
-        let right = (((tile_bounds.xmax - vrt_bounds.xmax) / xres).round()).max(0.);
-        let bottom = (((vrt_bounds.ymin - tile_bounds.ymin) / yres).round()).max(0.);
-        let top = (((tile_bounds.ymax - vrt_bounds.ymax) / yres).round()).max(0.);
+        let left = (((vrt_bounds.xmin - read_bounds.xmin) / xres).round()).max(0.);
+        let right = (((read_bounds.xmax - vrt_bounds.xmax) / xres).round()).max(0.);
+        let bottom = (((vrt_bounds.ymin - read_bounds.ymin) / yres).round()).max(0.);
+        let top = (((read_bounds.ymax - vrt_bounds.ymax) / yres).round()).max(0.);
 
         // calculate width and height in coordinates of VRT
         let width = (size - left - right).round() as usize;
@@ -177,7 +261,7 @@ impl Dataset {
             Some(ResampleAlg::NearestNeighbour),
         )?;
 
-        if all_equals(buffer, nodata) {
+        if all_nodata(buffer, nodata) {
             return Ok(false);
         }
 
@@ -192,10 +276,77 @@ impl Dataset {
             );
         }
 
+        if !self.clip.is_empty() {
+            // keep pixels with centers inside any clip bounds
+            let tile_xres = (tile_bounds.xmax - tile_bounds.xmin) / size;
+            let tile_yres = (tile_bounds.ymax - tile_bounds.ymin) / size;
+            let index =
+                |offset: f64, res: f64| (offset / res - 0.5).ceil().clamp(0., size) as usize;
+            let windows: Vec<(usize, usize, usize, usize)> = self
+                .clip
+                .iter()
+                .map(|clip| {
+                    (
+                        index(clip.xmin - tile_bounds.xmin, tile_xres),
+                        index(tile_bounds.ymax - clip.ymax, tile_yres),
+                        index(clip.xmax - tile_bounds.xmin, tile_xres),
+                        index(tile_bounds.ymax - clip.ymin, tile_yres),
+                    )
+                })
+                .collect();
+
+            if !windows.contains(&(0, 0, tile_size, tile_size)) {
+                fill_outside(buffer, (tile_size, tile_size), &windows, nodata);
+
+                if all_nodata(buffer, nodata) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        if let Some(cutline) = &self.cutline
+            && overlap == Overlap::Partial
+        {
+            let mut mask = vec![false; tile_size * tile_size];
+            cutline.mask(tile_bounds, tile_size, &mut mask);
+            for (value, inside) in buffer.iter_mut().zip(mask) {
+                if !inside {
+                    *value = nodata;
+                }
+            }
+
+            if all_nodata(buffer, nodata) {
+                return Ok(false);
+            }
+        }
+
         Ok(true)
     }
 }
 
+/// Transform bounds in geographic coordinates to the CRS of the tile matrix set
+pub fn geo_to_tms_bounds(bounds: &Bounds, tms: &TileMatrixSet) -> Result<Bounds, Box<dyn Error>> {
+    let src_crs = SpatialRef::from_definition("OGC:CRS84")?;
+    let transform = CoordTransform::new(&src_crs, &tms_spatial_ref(tms)?)?;
+    let out_bounds =
+        transform.transform_bounds(&[bounds.xmin, bounds.ymin, bounds.xmax, bounds.ymax], 21)?;
+
+    Ok(Bounds {
+        xmin: out_bounds[0],
+        ymin: out_bounds[1],
+        xmax: out_bounds[2],
+        ymax: out_bounds[3],
+    })
+}
+
+/// Create spatial reference for the CRS of the tile matrix set, using x /
+/// easting as the first axis regardless of the axis order of the CRS
+pub fn tms_spatial_ref(tms: &TileMatrixSet) -> Result<SpatialRef, Box<dyn Error>> {
+    let mut sp_ref = SpatialRef::from_definition(&tms.crs)?;
+    sp_ref.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+    Ok(sp_ref)
+}
+
 pub fn write_raster<T: GdalType + Copy>(
     path: String,
     width: usize,
@@ -228,3 +379,51 @@ pub fn write_raster<T: GdalType + Copy>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::write_fixture;
+
+    #[test]
+    fn tms_alpha_vrt() {
+        // RGB image without NODATA that is rotated by 30 degrees, so that the
+        // corners of the warped VRT are outside the image
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        let transform = Affine::new(100. * cos, 100. * sin, 0., 100. * sin, -100. * cos, 0.);
+        let path = write_fixture(
+            "rastertiler_alpha_vrt.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            3,
+            100u8,
+            None,
+        );
+
+        let dataset = Dataset::open(&path, false).unwrap();
+        let vrt = dataset
+            .tms_alpha_vrt(&TileMatrixSet::web_mercator_quad(), &[], None)
+            .unwrap();
+        assert_eq!(vrt.band_count(), 4);
+
+        // read a tile that covers the full extent of the VRT
+        let mut alpha = vec![0u8; 64 * 64];
+        let has_data = vrt
+            .read_tile(
+                &vrt.band(4).unwrap(),
+                &vrt.bounds().unwrap(),
+                64,
+                &mut alpha,
+                0u8,
+            )
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(has_data);
+        for corner in [0, 63, 63 * 64, 64 * 64 - 1] {
+            assert_eq!(alpha[corner], 0);
+        }
+        assert_eq!(alpha[32 * 64 + 32], 255);
+    }
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::png::TileFormat;
+use crate::tileid::TileID;
+use crate::writer::TileWriter;
+
+/// Row order of tiles written to a directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileScheme {
+    /// Rows are numbered from the top (north)
+    Xyz,
+    /// Rows are numbered from the bottom (south), as in MBTiles
+    Tms,
+}
+
+impl TileScheme {
+    pub fn name(&self) -> &str {
+        match self {
+            TileScheme::Xyz => "xyz",
+            TileScheme::Tms => "tms",
+        }
+    }
+}
+
+/// Writes tiles to {z}/{x}/{y}.{ext} files within a directory, along with a
+/// metadata.json file.  Existing files are overwritten.
+pub struct DirectoryWriter {
+    path: PathBuf,
+    extension: String,
+    scheme: TileScheme,
+}
+
+impl DirectoryWriter {
+    pub fn new(path: &PathBuf, format: TileFormat, scheme: TileScheme) -> Result<DirectoryWriter> {
+        fs::create_dir_all(path)?;
+
+        Ok(DirectoryWriter {
+            path: path.to_owned(),
+            extension: format.name().to_owned(),
+            scheme,
+        })
+    }
+}
+
+impl TileWriter for DirectoryWriter {
+    fn set_metadata(&self, metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+        let mut json = serde_json::Map::new();
+        for &(key, value) in metadata.iter() {
+            json.insert(key.to_owned(), serde_json::Value::from(value));
+        }
+        json.insert(
+            "scheme".to_owned(),
+            serde_json::Value::from(self.scheme.name()),
+        );
+
+        fs::write(
+            self.path.join("metadata.json"),
+            serde_json::to_string_pretty(&json)?,
+        )?;
+
+        Ok(())
+    }
+
+    fn write_tile(&self, tile_id: &TileID, tile_data: &[u8]) -> Result<()> {
+        let y = match self.scheme {
+            TileScheme::Xyz => tile_id.y,
+            TileScheme::Tms => (1u32 << tile_id.zoom as u32) - 1u32 - tile_id.y,
+        };
+
+        let dir = self
+            .path
+            .join(tile_id.zoom.to_string())
+            .join(tile_id.x.to_string());
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(format!("{}.{}", y, self.extension)), tile_data)?;
+
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(TileScheme::Xyz, "2/1/0.png")]
+    #[case(TileScheme::Tms, "2/1/3.png")]
+    fn write_tile(#[case] scheme: TileScheme, #[case] expected: &str) {
+        let path = std::env::temp_dir().join(format!("rastertiler_write_tile_{}", scheme.name()));
+        let writer = DirectoryWriter::new(&path, TileFormat::Png, scheme).unwrap();
+        writer.set_metadata(&[("name", "test")]).unwrap();
+        writer.write_tile(&TileID::new(2, 1, 0), b"tile").unwrap();
+
+        let tile_data = fs::read(path.join(expected)).unwrap();
+        let metadata = fs::read_to_string(path.join("metadata.json")).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(tile_data, b"tile");
+        let metadata: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(metadata["name"], "test");
+        assert_eq!(metadata["scheme"], scheme.name());
+    }
+}
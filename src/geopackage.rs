@@ -0,0 +1,324 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+
+use crate::bounds::Bounds;
+use crate::mbtiles::MBTiles;
+use crate::png::TileFormat;
+use crate::tileid::{TileID, CE, ORIGIN};
+use crate::writer::TileWriter;
+
+/// Name of the tile pyramid user data table
+const TABLE_NAME: &str = "tiles";
+
+const INIT_QUERY: &str = r#"
+PRAGMA journal_mode=WAL;
+PRAGMA application_id=1196444487;
+PRAGMA user_version=10400;
+
+CREATE TABLE IF NOT EXISTS gpkg_spatial_ref_sys (
+    srs_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL PRIMARY KEY,
+    organization TEXT NOT NULL,
+    organization_coordsys_id INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    description TEXT
+);
+
+CREATE TABLE IF NOT EXISTS gpkg_contents (
+    table_name TEXT NOT NULL PRIMARY KEY,
+    data_type TEXT NOT NULL,
+    identifier TEXT UNIQUE,
+    description TEXT DEFAULT '',
+    last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+    min_x DOUBLE,
+    min_y DOUBLE,
+    max_x DOUBLE,
+    max_y DOUBLE,
+    srs_id INTEGER,
+    CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+);
+
+CREATE TABLE IF NOT EXISTS gpkg_tile_matrix_set (
+    table_name TEXT NOT NULL PRIMARY KEY,
+    srs_id INTEGER NOT NULL,
+    min_x DOUBLE NOT NULL,
+    min_y DOUBLE NOT NULL,
+    max_x DOUBLE NOT NULL,
+    max_y DOUBLE NOT NULL,
+    CONSTRAINT fk_gtms_table_name FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+    CONSTRAINT fk_gtms_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+);
+
+CREATE TABLE IF NOT EXISTS gpkg_tile_matrix (
+    table_name TEXT NOT NULL,
+    zoom_level INTEGER NOT NULL,
+    matrix_width INTEGER NOT NULL,
+    matrix_height INTEGER NOT NULL,
+    tile_width INTEGER NOT NULL,
+    tile_height INTEGER NOT NULL,
+    pixel_x_size DOUBLE NOT NULL,
+    pixel_y_size DOUBLE NOT NULL,
+    CONSTRAINT pk_ttm PRIMARY KEY (table_name, zoom_level),
+    CONSTRAINT fk_tmm_table_name FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name)
+);
+
+CREATE TABLE IF NOT EXISTS gpkg_extensions (
+    table_name TEXT,
+    column_name TEXT,
+    extension_name TEXT NOT NULL,
+    definition TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    CONSTRAINT ge_tce UNIQUE (table_name, column_name, extension_name)
+);
+
+CREATE TABLE IF NOT EXISTS tiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    zoom_level INTEGER NOT NULL,
+    tile_column INTEGER NOT NULL,
+    tile_row INTEGER NOT NULL,
+    tile_data BLOB NOT NULL,
+    UNIQUE (zoom_level, tile_column, tile_row)
+);
+"#;
+
+const WGS84_WKT: &str = r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]]"#;
+
+const MERCATOR_WKT: &str = r#"PROJCS["WGS 84 / Pseudo-Mercator",GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]],PROJECTION["Mercator_1SP"],PARAMETER["central_meridian",0],PARAMETER["scale_factor",1],PARAMETER["false_easting",0],PARAMETER["false_northing",0],UNIT["metre",1,AUTHORITY["EPSG","9001"]],AXIS["X",EAST],AXIS["Y",NORTH],EXTENSION["PROJ4","+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +wktext +no_defs"],AUTHORITY["EPSG","3857"]]"#;
+
+const INSERT_SRS_QUERY: &str = "INSERT OR IGNORE INTO gpkg_spatial_ref_sys (srs_name, srs_id, organization, organization_coordsys_id, definition, description) VALUES (?, ?, ?, ?, ?, ?)";
+const INSERT_CONTENTS_QUERY: &str = "INSERT INTO gpkg_contents (table_name, data_type, identifier, min_x, min_y, max_x, max_y, srs_id) VALUES (?, 'tiles', ?, ?, ?, ?, ?, 3857)";
+const INSERT_TILE_MATRIX_SET_QUERY: &str = "INSERT INTO gpkg_tile_matrix_set (table_name, srs_id, min_x, min_y, max_x, max_y) VALUES (?, 3857, ?, ?, ?, ?)";
+const INSERT_TILE_MATRIX_QUERY: &str = "INSERT INTO gpkg_tile_matrix (table_name, zoom_level, matrix_width, matrix_height, tile_width, tile_height, pixel_x_size, pixel_y_size) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+const INSERT_WEBP_EXTENSION_QUERY: &str = "INSERT INTO gpkg_extensions (table_name, column_name, extension_name, definition, scope) VALUES (?, 'tile_data', 'gpkg_webp', 'http://www.geopackage.org/spec/#extension_tiles_webp', 'read-write')";
+const UPDATE_CONTENTS_QUERY: &str =
+    "UPDATE gpkg_contents SET identifier = ?, description = ? WHERE table_name = ?";
+const INSERT_TILE_QUERY: &str =
+    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?, ?, ?, ?)";
+
+/// OGC GeoPackage tile pyramid writer for the Web Mercator (EPSG:3857) tile
+/// grid.  Tiles are written to the "tiles" table.
+pub struct GeoPackage {
+    pool: r2d2::Pool<SqliteConnectionManager>,
+}
+
+impl GeoPackage {
+    pub fn new(
+        path: &PathBuf,
+        pool_size: u8,
+        format: TileFormat,
+        tilesize: u16,
+        minzoom: u8,
+        maxzoom: u8,
+        mercator_bounds: &Bounds,
+    ) -> Result<GeoPackage> {
+        // always overwrite existing database
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+
+        let manager =
+            SqliteConnectionManager::file(path).with_init(|c| c.execute_batch(INIT_QUERY));
+
+        let pool = r2d2::Pool::builder()
+            .max_size(pool_size as u32)
+            .build(manager)?;
+
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+
+        // run queries in a block so that query goes out of scope before tx
+        {
+            let mut query = tx.prepare(INSERT_SRS_QUERY)?;
+            query.execute(params![
+                "Undefined cartesian SRS",
+                -1,
+                "NONE",
+                -1,
+                "undefined",
+                "undefined cartesian coordinate reference system"
+            ])?;
+            query.execute(params![
+                "Undefined geographic SRS",
+                0,
+                "NONE",
+                0,
+                "undefined",
+                "undefined geographic coordinate reference system"
+            ])?;
+            query.execute(params![
+                "WGS 84 geodetic",
+                4326,
+                "EPSG",
+                4326,
+                WGS84_WKT,
+                "longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid"
+            ])?;
+            query.execute(params![
+                "WGS 84 / Pseudo-Mercator",
+                3857,
+                "EPSG",
+                3857,
+                MERCATOR_WKT,
+                "Web Mercator"
+            ])?;
+
+            tx.execute(
+                INSERT_CONTENTS_QUERY,
+                params![
+                    TABLE_NAME,
+                    TABLE_NAME,
+                    mercator_bounds.xmin,
+                    mercator_bounds.ymin,
+                    mercator_bounds.xmax,
+                    mercator_bounds.ymax
+                ],
+            )?;
+
+            tx.execute(
+                INSERT_TILE_MATRIX_SET_QUERY,
+                params![TABLE_NAME, -ORIGIN, -ORIGIN, ORIGIN, ORIGIN],
+            )?;
+
+            let mut query = tx.prepare(INSERT_TILE_MATRIX_QUERY)?;
+            for zoom in minzoom..(maxzoom + 1) {
+                let size = 1u32 << zoom as u32;
+                let pixel_size = CE / (size as f64 * tilesize as f64);
+                query.execute(params![
+                    TABLE_NAME, zoom, size, size, tilesize, tilesize, pixel_size, pixel_size
+                ])?;
+            }
+
+            // WebP tiles are supported as a registered extension
+            if format == TileFormat::WebP {
+                tx.execute(INSERT_WEBP_EXTENSION_QUERY, params![TABLE_NAME])?;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(GeoPackage { pool })
+    }
+
+    /// Change the database back to non-WAL mode
+    pub fn flush(path: &PathBuf) -> Result<()> {
+        MBTiles::flush(path)
+    }
+}
+
+impl TileWriter for GeoPackage {
+    /// Set the identifier and description of the tiles table from the name
+    /// and description in metadata; other metadata are not stored
+    fn set_metadata(&self, metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+        let conn = self.pool.get()?;
+
+        let get = |key: &str| {
+            metadata
+                .iter()
+                .find(|&&(k, _)| k == key)
+                .map(|&(_, value)| value)
+        };
+
+        conn.execute(
+            UPDATE_CONTENTS_QUERY,
+            params![
+                get("name").unwrap_or(TABLE_NAME),
+                get("description").unwrap_or(""),
+                TABLE_NAME
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn write_tile(&self, tile_id: &TileID, tile_data: &[u8]) -> Result<()> {
+        let conn = self.pool.get()?;
+
+        // GeoPackage tile rows are numbered from the top, same as tile IDs
+        let mut query = conn.prepare_cached(INSERT_TILE_QUERY)?;
+        query.execute(params![tile_id.zoom, tile_id.x, tile_id.y, tile_data])?;
+
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn write_geopackage() {
+        let path = std::env::temp_dir().join("rastertiler_write_geopackage.gpkg");
+        let bounds = Bounds {
+            xmin: -1000.,
+            ymin: -1000.,
+            xmax: 1000.,
+            ymax: 1000.,
+        };
+
+        // in a block so that connections are closed before flush
+        {
+            let gpkg = GeoPackage::new(&path, 1, TileFormat::WebP, 256, 1, 2, &bounds).unwrap();
+            gpkg.set_metadata(&[("name", "test")]).unwrap();
+            gpkg.write_tile(&TileID::new(2, 1, 0), b"tile").unwrap();
+            gpkg.finish().unwrap();
+        }
+        GeoPackage::flush(&path).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let application_id: i64 = conn
+            .query_row("PRAGMA application_id", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(application_id, 0x47504B47);
+
+        let identifier: String = conn
+            .query_row("SELECT identifier FROM gpkg_contents", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(identifier, "test");
+
+        let matrix: Vec<(u8, u32, f64)> = conn
+            .prepare("SELECT zoom_level, matrix_width, pixel_x_size FROM gpkg_tile_matrix ORDER BY zoom_level")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(matrix.len(), 2);
+        assert_eq!(matrix[0].0, 1);
+        assert_eq!(matrix[1].1, 4);
+        assert!((matrix[1].2 - CE / 1024.).abs() < 1e-9);
+
+        let extensions: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM gpkg_extensions WHERE extension_name = 'gpkg_webp'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(extensions, 1);
+
+        let (x, y, data): (u32, u32, Vec<u8>) = conn
+            .query_row(
+                "SELECT tile_column, tile_row, tile_data FROM tiles WHERE zoom_level = 2",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        drop(conn);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((x, y, data.as_slice()), (1, 0, b"tile".as_slice()));
+    }
+}
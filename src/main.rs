@@ -7,15 +7,30 @@ use clap::{CommandFactory, Parser, Subcommand};
 mod affine;
 mod array;
 mod bounds;
+mod cutline;
 mod dataset;
+mod directory;
+mod geopackage;
 mod mbtiles;
+mod pmtiles;
 mod png;
 mod render;
 mod tileid;
+mod tilejson;
+mod tms;
 mod window;
+mod writer;
 
+use crate::bounds::Bounds;
+use crate::directory::TileScheme;
 use crate::mbtiles::merge;
+use crate::png::{
+    ClassEdges, ColorRamp, ElevationEncoding, ElevationFormat, Interpolation, QuantizeMethod,
+    Quantizer, TileFormat, DEFAULT_JPEG_QUALITY, DEFAULT_QUANTIZE_MAX_ERROR,
+};
 use crate::render::render_tiles;
+use crate::tms::{TileMatrixSet, ZoomLevel};
+use crate::writer::OutputType;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
@@ -25,6 +40,7 @@ struct Cli {
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     #[command(about = "merge two MBTiles files into a single MBTiles file")]
     Merge {
@@ -37,27 +53,65 @@ enum Commands {
         #[arg(name = "output MBTiles file")]
         out: PathBuf,
     },
-    #[command(about = "render a single-band GeoTIFF to a MBTiles file")]
+    #[command(about = "render a single-band or RGB GeoTIFF to a MBTiles or PMTiles file")]
     Render {
         #[arg(value_parser=file_exists)]
         /// Input GeoTIFF filename
         tiff: PathBuf,
 
-        /// Output MBTiles filename
-        mbtiles: PathBuf,
+        /// Output MBTiles filename, PMTiles filename if the extension is
+        /// ".pmtiles", GeoPackage filename if the extension is ".gpkg", or
+        /// directory of {z}/{x}/{y} tiles if the path ends with "/", e.g.,
+        /// "tiles/"
+        output: PathBuf,
 
-        /// Minimum zoom level
-        #[clap(short = 'Z', long, default_value_t = 0, value_parser=parse_zoom)]
-        minzoom: u8,
+        /// Minimum zoom level, or "auto" for the zoom level at which the
+        /// dataset fits within about one tile
+        #[clap(short = 'Z', long, default_value = "0", value_parser=parse_zoom)]
+        minzoom: ZoomLevel,
 
-        /// Maximum zoom level
-        #[clap(short = 'z', long, default_value_t = 0, value_parser=parse_zoom)]
-        maxzoom: u8,
+        /// Maximum zoom level, or "auto" for the zoom level with a resolution
+        /// closest to the native resolution of the dataset
+        #[clap(short = 'z', long, default_value = "0", value_parser=parse_zoom)]
+        maxzoom: ZoomLevel,
 
         /// Tile size in pixels per side
         #[clap(short = 's', long, default_value_t = 512)]
         tilesize: u16,
 
+        /// Tile image format, as one of "png", "webp" (lossless), "jpg", "mixed"
+        /// (JPEG for fully opaque tiles, otherwise PNG), or "data" (raw values
+        /// as zstd-compressed arrays)
+        #[clap(short = 'f', long, default_value = "png", value_parser=parse_format)]
+        format: TileFormat,
+
+        /// JPEG quality, between 1 and 100; only used for "jpg" or "mixed" format
+        #[clap(short = 'q', long, default_value_t = DEFAULT_JPEG_QUALITY, value_parser=parse_quality)]
+        quality: u8,
+
+        /// Tile row order for directory output, as one of "xyz" or "tms"
+        #[clap(long, default_value = "xyz", value_parser=parse_scheme)]
+        scheme: TileScheme,
+
+        /// Tile matrix set that defines the CRS and grid of tiles, as one of
+        /// "WebMercatorQuad" or "WorldCRS84Quad", or an OGC TileMatrixSet JSON
+        /// file; only "WebMercatorQuad" can be used for MBTiles, PMTiles, or
+        /// GeoPackage output
+        #[clap(long, default_value = "WebMercatorQuad", value_parser=parse_tile_matrix_set)]
+        tile_matrix_set: TileMatrixSet,
+
+        /// Only render tiles within bounds in geographic coordinates, as
+        /// "xmin,ymin,xmax,ymax", where xmin is greater than xmax for bounds
+        /// that cross the antimeridian; pixels outside bounds are transparent
+        #[clap(long, value_parser=parse_bounds)]
+        bounds: Option<Bounds>,
+
+        /// Only render pixels within polygons of a vector file supported by
+        /// GDAL, e.g., GeoJSON, Shapefile, or GeoPackage; pixels outside are
+        /// transparent
+        #[clap(long)]
+        cutline: Option<PathBuf>,
+
         /// Tileset name
         #[clap(short = 'n', long)]
         name: Option<String>,
@@ -70,15 +124,139 @@ enum Commands {
         #[clap(short = 'a', long)]
         attribution: Option<String>,
 
+        /// Write a TileJSON 3.0 document describing the tileset to this path
+        #[clap(long, requires = "tile_url")]
+        tilejson: Option<PathBuf>,
+
+        /// URL template of tiles for TileJSON, e.g.,
+        /// "https://example.com/tiles/{z}/{x}/{y}.png"
+        #[clap(long, requires = "tilejson")]
+        tile_url: Option<String>,
+
         /// Number of workers to create tiles
         #[clap(short = 'w', long, default_value_t = 4)]
         workers: u8,
 
         /// Colormap as comma-delmited value:hex color pairs, e.g., "<value>:<hex>,<value:hex>"
-        /// can only be provided for uint8 data
+        /// can only be provided for uint8, uint16, int16, or uint32 data.
+        /// Alternatively, a named palette with range, e.g., "viridis:0:100",
+        /// which is rendered as a color ramp
         #[clap(short = 'c', long)]
         colormap: Option<String>,
 
+        /// Colormap file, as a GDAL color-relief text file (.txt), QGIS
+        /// exported color map (.clr), QGIS style file (.qml), or JSON file
+        /// (.json); can only be provided for uint8, uint16, int16, or uint32 data
+        #[clap(long, value_parser=file_exists, conflicts_with = "colormap")]
+        colormap_file: Option<PathBuf>,
+
+        /// Do not use the color table of a uint8 GeoTIFF as colormap if
+        /// colormap is not provided, and instead render to grayscale
+        #[clap(long, action)]
+        no_color_table: bool,
+
+        /// Minimum value used to stretch uint16 / int16 / float data to 8-bit grayscale;
+        /// detected from the data if not provided
+        #[clap(long, allow_hyphen_values = true)]
+        min: Option<f64>,
+
+        /// Maximum value used to stretch uint16 / int16 / float data to 8-bit grayscale;
+        /// detected from the data if not provided
+        #[clap(long, allow_hyphen_values = true)]
+        max: Option<f64>,
+
+        /// Color ramp as comma-delimited value:hex color stops, e.g., "<value>:<hex>,<value>:<hex>",
+        /// or a named palette with range, e.g., "viridis:0:100"; colors are
+        /// linearly interpolated between stops. Integer data are read as
+        /// float64 values
+        #[clap(short = 'r', long, allow_hyphen_values = true)]
+        ramp: Option<String>,
+
+        /// Write color ramp as 8-bit paletted PNGs with 255 colors sampled from
+        /// the ramp instead of RGB PNGs
+        #[clap(long, action)]
+        ramp_palette: bool,
+
+        /// Color space used to interpolate colors of color ramp, as one of
+        /// "rgb" or "lab"
+        #[clap(long, default_value = "rgb", value_parser=parse_interpolation)]
+        ramp_interpolation: Interpolation,
+
+        /// Classes as comma-delimited range:hex color pairs that bin values
+        /// into colors, e.g., "0-10:<hex>,10-25:<hex>,25+:<hex>"; ranges are
+        /// "<min>-<max>", "<min>+", or "<<max>"
+        #[clap(
+            long,
+            allow_hyphen_values = true,
+            conflicts_with_all = ["colormap", "colormap_file", "ramp"]
+        )]
+        classes: Option<String>,
+
+        /// Edge of each class range that is inclusive, as one of "lower"
+        /// (min <= value < max) or "upper" (min < value <= max)
+        #[clap(long, default_value = "lower", value_parser=parse_class_edges, requires = "classes")]
+        class_edges: ClassEdges,
+
+        /// Encode elevation values to RGB PNGs for use as terrain, as one of
+        /// "mapbox" (Terrain-RGB) or "terrarium"
+        #[clap(
+            long,
+            value_parser=parse_elevation,
+            conflicts_with_all = ["colormap", "colormap_file", "ramp", "classes"]
+        )]
+        elevation: Option<ElevationFormat>,
+
+        /// Base elevation value for elevation encoding; defaults to -10000 for
+        /// mapbox and -32768 for terrarium
+        #[clap(long, allow_hyphen_values = true, requires = "elevation")]
+        elevation_base: Option<f64>,
+
+        /// Elevation interval for elevation encoding; defaults to 0.1 for
+        /// mapbox and 1/256 for terrarium
+        #[clap(long, requires = "elevation")]
+        elevation_interval: Option<f64>,
+
+        /// Render bands 1-3 of a uint8 GeoTIFF as RGB, using band 4 as alpha
+        /// if it is an alpha band; otherwise pixels outside the dataset or
+        /// NODATA are transparent
+        #[clap(
+            long,
+            action,
+            conflicts_with_all = ["colormap", "colormap_file", "ramp", "classes", "elevation"]
+        )]
+        rgb: bool,
+
+        /// Render uint16 data as 16-bit grayscale PNG tiles that preserve values
+        /// exactly instead of stretching them to 8-bit grayscale; NODATA is
+        /// transparent
+        #[clap(
+            long,
+            action,
+            conflicts_with_all = ["colormap", "colormap_file", "ramp", "classes", "elevation", "rgb", "min", "max"]
+        )]
+        gray16: bool,
+
+        /// Quantize uint32 RGB tiles with more than 256 colors to a palette of
+        /// 255 colors so they can be written as paletted PNGs, using one of
+        /// "median-cut" or "kmeans"
+        #[clap(long, value_parser=parse_quantize)]
+        quantize: Option<QuantizeMethod>,
+
+        /// Use dithering when quantizing tiles
+        #[clap(long, action, requires = "quantize")]
+        dither: bool,
+
+        /// Maximum difference allowed in any color channel of any pixel when
+        /// quantizing tiles; tiles that exceed this are not quantized. Use 0 to
+        /// only allow exact colors
+        #[clap(long, default_value_t = DEFAULT_QUANTIZE_MAX_ERROR, requires = "quantize")]
+        quantize_max_error: u8,
+
+        /// Opacity of rendered tiles, between 0 (transparent) and 1 (opaque);
+        /// multiplied with the alpha of colormap entries
+        #[clap(long, default_value_t = 1., value_parser=parse_opacity, conflicts_with = "elevation")]
+        opacity: f64,
+
         /// Disable use of overviews in source GeoTIFF. This will yield more precise
         /// results at the expense of slower performance
         #[clap(long, action)]
@@ -94,18 +272,46 @@ fn main() -> Result<()> {
         }
         Commands::Render {
             tiff,
-            mbtiles,
+            output,
             minzoom,
             maxzoom,
             tilesize,
+            format,
+            quality,
+            scheme,
+            tile_matrix_set,
+            bounds,
+            cutline,
             name,
             description,
             attribution,
+            tilejson,
+            tile_url,
             workers,
             colormap,
+            colormap_file,
+            min,
+            max,
+            ramp,
+            ramp_palette,
+            ramp_interpolation,
+            classes,
+            class_edges,
+            elevation,
+            elevation_base,
+            elevation_interval,
+            rgb,
+            gray16,
+            no_color_table,
+            quantize,
+            dither,
+            quantize_max_error,
+            opacity,
             disable_overviews,
         } => {
-            if minzoom > maxzoom {
+            if let (ZoomLevel::Level(minzoom), ZoomLevel::Level(maxzoom)) = (minzoom, maxzoom)
+                && minzoom > maxzoom
+            {
                 let mut cmd = Cli::command();
                 cmd.error(
                     ErrorKind::ArgumentConflict,
@@ -114,17 +320,188 @@ fn main() -> Result<()> {
                 .exit();
             }
 
+            if let (Some(min), Some(max)) = (min, max)
+                && min >= max
+            {
+                let mut cmd = Cli::command();
+                cmd.error(ErrorKind::ArgumentConflict, "min must be less than max")
+                    .exit();
+            }
+
+            if let Some(interval) = elevation_interval
+                && *interval <= 0.
+            {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    "elevation interval must be greater than 0",
+                )
+                .exit();
+            }
+
+            let format = format.with_quality(*quality);
+            if let TileFormat::Jpeg { .. } | TileFormat::Mixed { .. } = format {
+                if elevation.is_some() {
+                    let mut cmd = Cli::command();
+                    cmd.error(
+                        ErrorKind::ArgumentConflict,
+                        "elevation cannot be encoded using lossy jpg or mixed format",
+                    )
+                    .exit();
+                }
+
+                if matches!(format, TileFormat::Jpeg { .. }) && *opacity < 1. {
+                    let mut cmd = Cli::command();
+                    cmd.error(
+                        ErrorKind::ArgumentConflict,
+                        "opacity cannot be used with jpg format",
+                    )
+                    .exit();
+                }
+            }
+
+            let output_type = OutputType::from_path(output);
+            if *scheme == TileScheme::Tms && output_type != OutputType::Directory {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::ArgumentConflict,
+                    "tms scheme can only be used for directory output",
+                )
+                .exit();
+            }
+
+            if !tile_matrix_set.is_web_mercator()
+                && (output_type != OutputType::Directory || *scheme == TileScheme::Tms)
+            {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::ArgumentConflict,
+                    "tile matrix sets other than WebMercatorQuad can only be used for directory output with xyz scheme",
+                )
+                .exit();
+            }
+
+            if output_type == OutputType::Directory && matches!(format, TileFormat::Mixed { .. }) {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::ArgumentConflict,
+                    "mixed format cannot be used for directory output",
+                )
+                .exit();
+            }
+
+            if format == TileFormat::Data {
+                if colormap.is_some()
+                    || colormap_file.is_some()
+                    || ramp.is_some()
+                    || classes.is_some()
+                    || elevation.is_some()
+                    || *rgb
+                    || *opacity < 1.
+                {
+                    let mut cmd = Cli::command();
+                    cmd.error(
+                        ErrorKind::ArgumentConflict,
+                        "data format cannot be used with colormap, ramp, classes, elevation, rgb, or opacity",
+                    )
+                    .exit();
+                }
+
+                if output_type == OutputType::GeoPackage {
+                    let mut cmd = Cli::command();
+                    cmd.error(
+                        ErrorKind::ArgumentConflict,
+                        "data format cannot be used for GeoPackage output",
+                    )
+                    .exit();
+                }
+            }
+
+            // named palettes provided as colormap, e.g., "viridis:0:100", are
+            // rendered as color ramps
+            let (colormap, ramp) = match colormap {
+                Some(c) if ColorRamp::is_named(c) => {
+                    if ramp.is_some() {
+                        let mut cmd = Cli::command();
+                        cmd.error(
+                            ErrorKind::ArgumentConflict,
+                            "named palette colormap cannot be used with ramp",
+                        )
+                        .exit();
+                    }
+                    (None, Some(c.to_owned()))
+                }
+                _ => (colormap.to_owned(), ramp.to_owned()),
+            };
+
+            if *ramp_palette && ramp.is_none() {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::MissingRequiredArgument,
+                    "ramp palette requires ramp or a named palette colormap",
+                )
+                .exit();
+            }
+
+            if *gray16 && (format != TileFormat::Png || *opacity < 1.) {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::ArgumentConflict,
+                    "gray16 can only be used with png format and without opacity",
+                )
+                .exit();
+            }
+
+            if quantize.is_some() && format != TileFormat::Png {
+                let mut cmd = Cli::command();
+                cmd.error(
+                    ErrorKind::ArgumentConflict,
+                    "quantize can only be used with png format",
+                )
+                .exit();
+            }
+
+            let quantizer = quantize.map(|method| {
+                Quantizer::new(method)
+                    .with_dither(*dither)
+                    .with_max_error(*quantize_max_error)
+            });
+
+            let elevation =
+                elevation.map(|e| ElevationEncoding::new(e, *elevation_base, *elevation_interval));
+
             render_tiles(
                 tiff,
-                mbtiles,
+                output,
                 *minzoom,
                 *maxzoom,
                 *tilesize,
+                format,
+                *scheme,
+                tile_matrix_set,
+                *bounds,
+                cutline,
                 name,
                 description,
                 attribution,
+                tilejson,
+                tile_url,
                 *workers,
-                colormap,
+                &colormap,
+                colormap_file,
+                *min,
+                *max,
+                &ramp,
+                *ramp_palette,
+                *ramp_interpolation,
+                classes,
+                *class_edges,
+                elevation,
+                *rgb,
+                *gray16,
+                *no_color_table,
+                quantizer,
+                (opacity * 255.).round() as u8,
                 *disable_overviews,
             )?;
         }
@@ -143,18 +520,130 @@ fn file_exists(s: &str) -> Result<PathBuf, String> {
     Ok(path)
 }
 
-fn parse_zoom(s: &str) -> Result<u8, String> {
+fn parse_zoom(s: &str) -> Result<ZoomLevel, String> {
+    if s == "auto" {
+        return Ok(ZoomLevel::Auto);
+    }
     let zoom = s
         .parse()
         .map_err(|_| format!("`{}` isn't a valid number", s))?;
     if zoom > 24 {
         return Err(String::from("must be no greater than 24"));
     }
-    Ok(zoom)
+    Ok(ZoomLevel::Level(zoom))
+}
+
+fn parse_opacity(s: &str) -> Result<f64, String> {
+    let opacity: f64 = s
+        .parse()
+        .map_err(|_| format!("`{}` isn't a valid number", s))?;
+    if !(0. ..=1.).contains(&opacity) {
+        return Err(String::from("must be between 0 and 1"));
+    }
+    Ok(opacity)
+}
+
+fn parse_quality(s: &str) -> Result<u8, String> {
+    let quality = s
+        .parse()
+        .map_err(|_| format!("`{}` isn't a valid number", s))?;
+    if !(1..=100).contains(&quality) {
+        return Err(String::from("must be between 1 and 100"));
+    }
+    Ok(quality)
+}
+
+fn parse_format(s: &str) -> Result<TileFormat, String> {
+    match s {
+        "png" => Ok(TileFormat::Png),
+        "webp" => Ok(TileFormat::WebP),
+        "jpg" | "jpeg" => Ok(TileFormat::Jpeg {
+            quality: DEFAULT_JPEG_QUALITY,
+        }),
+        "mixed" => Ok(TileFormat::Mixed {
+            quality: DEFAULT_JPEG_QUALITY,
+        }),
+        "data" => Ok(TileFormat::Data),
+        _ => Err(String::from("must be one of: png, webp, jpg, mixed, data")),
+    }
+}
+
+fn parse_scheme(s: &str) -> Result<TileScheme, String> {
+    match s {
+        "xyz" => Ok(TileScheme::Xyz),
+        "tms" => Ok(TileScheme::Tms),
+        _ => Err(String::from("must be one of: xyz, tms")),
+    }
+}
+
+fn parse_bounds(s: &str) -> Result<Bounds, String> {
+    let bounds = Bounds::parse(s)?;
+    if bounds.ymin < -90. || bounds.ymax > 90. {
+        return Err(String::from("bounds must be within -90 to 90 latitude"));
+    }
+
+    // bounds that cross the antimeridian have xmin greater than xmax, or one
+    // of xmin or xmax beyond -180 or 180
+    let valid = match (bounds.xmin, bounds.xmax) {
+        (xmin, xmax) if xmin > xmax => xmin <= 180. && xmax >= -180.,
+        (xmin, xmax) if xmin < -180. => xmin >= -360. && xmax <= 180. && xmax - xmin <= 360.,
+        (xmin, xmax) if xmax > 180. => xmin >= -180. && xmax <= 360. && xmax - xmin <= 360.,
+        _ => true,
+    };
+    if !valid {
+        return Err(String::from(
+            "bounds must be within -180 to 180 longitude; to cross the antimeridian, xmin may be greater than xmax, or either xmin or xmax may be beyond -180 or 180",
+        ));
+    }
+    Ok(bounds)
+}
+
+fn parse_tile_matrix_set(s: &str) -> Result<TileMatrixSet, String> {
+    TileMatrixSet::from_name_or_file(s).map_err(|e| format!("invalid tile matrix set: {}", e))
+}
+
+fn parse_quantize(s: &str) -> Result<QuantizeMethod, String> {
+    match s {
+        "median-cut" => Ok(QuantizeMethod::MedianCut),
+        "kmeans" => Ok(QuantizeMethod::KMeans),
+        _ => Err(String::from("must be one of: median-cut, kmeans")),
+    }
+}
+
+fn parse_interpolation(s: &str) -> Result<Interpolation, String> {
+    match s {
+        "rgb" => Ok(Interpolation::Rgb),
+        "lab" => Ok(Interpolation::Lab),
+        _ => Err(String::from("must be one of: rgb, lab")),
+    }
+}
+
+fn parse_class_edges(s: &str) -> Result<ClassEdges, String> {
+    match s {
+        "lower" => Ok(ClassEdges::Lower),
+        "upper" => Ok(ClassEdges::Upper),
+        _ => Err(String::from("must be one of: lower, upper")),
+    }
+}
+
+fn parse_elevation(s: &str) -> Result<ElevationFormat, String> {
+    match s {
+        "mapbox" => Ok(ElevationFormat::TerrainRGB),
+        "terrarium" => Ok(ElevationFormat::Terrarium),
+        _ => Err(String::from("must be one of: mapbox, terrarium")),
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::path::PathBuf;
+
+    use gdal::raster::{
+        Buffer, ColorEntry, ColorTable, GdalType, PaletteInterpretation, RasterCreationOptions,
+    };
+    use gdal::spatial_ref::SpatialRef;
+    use gdal::DriverManager;
+
     use crate::affine::Affine;
     use crate::bounds::Bounds;
 
@@ -177,4 +666,70 @@ mod test {
             && approx_eq(l.xmax, r.xmax, precision)
             && approx_eq(l.ymax, r.ymax, precision)
     }
+
+    /// Write a GeoTIFF to the temp directory, with all pixels of all bands set
+    /// to value
+    pub fn write_fixture<T: GdalType + Copy>(
+        name: &str,
+        size: (usize, usize),
+        transform: &Affine,
+        crs: &str,
+        band_count: usize,
+        value: T,
+        nodata: Option<f64>,
+    ) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+        let options = RasterCreationOptions::from_iter(["INTERLEAVE=BAND"]);
+        let mut dataset = driver
+            .create_with_band_type_with_options::<T, _>(&path, size.0, size.1, band_count, &options)
+            .unwrap();
+        dataset.set_geo_transform(&transform.to_gdal()).unwrap();
+        dataset
+            .set_spatial_ref(&SpatialRef::from_definition(crs).unwrap())
+            .unwrap();
+
+        for i in 1..=band_count {
+            let mut band = dataset.rasterband(i).unwrap();
+            band.set_no_data_value(nodata).unwrap();
+            let mut buffer = Buffer::new(size, vec![value; size.0 * size.1]);
+            band.write((0, 0), size, &mut buffer).unwrap();
+        }
+
+        path
+    }
+
+    /// Write single-band uint8 GeoTIFF fixture with a color table of RGBA
+    /// colors and no NODATA value
+    pub fn write_palette_fixture(
+        name: &str,
+        size: (usize, usize),
+        transform: &Affine,
+        crs: &str,
+        value: u8,
+        colors: &[(i16, i16, i16, i16)],
+    ) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+        let options = RasterCreationOptions::from_iter(["INTERLEAVE=BAND"]);
+        let mut dataset = driver
+            .create_with_band_type_with_options::<u8, _>(&path, size.0, size.1, 1, &options)
+            .unwrap();
+        dataset.set_geo_transform(&transform.to_gdal()).unwrap();
+        dataset
+            .set_spatial_ref(&SpatialRef::from_definition(crs).unwrap())
+            .unwrap();
+
+        let mut color_table = ColorTable::new(PaletteInterpretation::Rgba);
+        for (i, &(r, g, b, a)) in colors.iter().enumerate() {
+            color_table.set_color_entry(i as u16, &ColorEntry::rgba(r, g, b, a));
+        }
+
+        let mut band = dataset.rasterband(1).unwrap();
+        band.set_color_table(&color_table);
+        let mut buffer = Buffer::new(size, vec![value; size.0 * size.1]);
+        band.write((0, 0), size, &mut buffer).unwrap();
+
+        path
+    }
 }
@@ -9,6 +9,7 @@ use rusqlite::{params, Connection};
 use seahash::hash;
 
 use crate::tileid::TileID;
+use crate::writer::TileWriter;
 
 const INIT_QUERY: &str = r#"
 PRAGMA journal_mode=WAL;
@@ -78,7 +79,30 @@ impl MBTiles {
         Ok(self.pool.get()?)
     }
 
-    pub fn set_metadata(&self, metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+    pub fn flush(path: &PathBuf) -> Result<()> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(RESET_WAL_QUERY)?;
+
+        // delete -wal and -shm files if exist
+        let path_str = path.to_str().unwrap();
+        let mut shm_path = PathBuf::new();
+        shm_path.push(format!("{}-shm", path_str));
+        if shm_path.exists() {
+            fs::remove_file(shm_path)?;
+        }
+
+        let mut wal_path = PathBuf::new();
+        wal_path.push(format!("{}-wal", path_str));
+        if wal_path.exists() {
+            fs::remove_file(wal_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TileWriter for MBTiles {
+    fn set_metadata(&self, metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
         let mut conn = self.pool.get().unwrap();
         let tx = conn.transaction()?;
 
@@ -95,16 +119,12 @@ impl MBTiles {
         Ok(())
     }
 
-    pub fn write_tile(
-        &self,
-        conn: &PooledConnection<SqliteConnectionManager>,
-        tile_id: &TileID,
-        png_data: &[u8],
-    ) -> Result<()> {
-        let id = hash(png_data) as i64;
+    fn write_tile(&self, tile_id: &TileID, tile_data: &[u8]) -> Result<()> {
+        let conn = self.pool.get()?;
+        let id = hash(tile_data) as i64;
 
         let mut query = conn.prepare_cached(INSERT_TILE_DATA_QUERY)?;
-        query.execute(params![id, png_data])?;
+        query.execute(params![id, tile_data])?;
 
         let mut query = conn.prepare_cached(INSERT_TILE_QUERY)?;
 
@@ -115,33 +135,13 @@ impl MBTiles {
         Ok(())
     }
 
-    pub fn update_index(&self) -> Result<()> {
+    /// Create the tile index
+    fn finish(&self) -> Result<()> {
         let conn = self.pool.get().unwrap();
         conn.execute_batch(UPDATE_INDEX_QUERY)?;
 
         Ok(())
     }
-
-    pub fn flush(path: &PathBuf) -> Result<()> {
-        let conn = Connection::open(path)?;
-        conn.execute_batch(RESET_WAL_QUERY)?;
-
-        // delete -wal and -shm files if exist
-        let path_str = path.to_str().unwrap();
-        let mut shm_path = PathBuf::new();
-        shm_path.push(format!("{}-shm", path_str));
-        if shm_path.exists() {
-            fs::remove_file(shm_path)?;
-        }
-
-        let mut wal_path = PathBuf::new();
-        wal_path.push(format!("{}-wal", path_str));
-        if wal_path.exists() {
-            fs::remove_file(wal_path)?;
-        }
-
-        Ok(())
-    }
 }
 
 pub fn merge(left: &PathBuf, right: &Path, out: &PathBuf) -> Result<()> {
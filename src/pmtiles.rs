@@ -0,0 +1,512 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use seahash::hash;
+
+use crate::bounds::Bounds;
+use crate::png::TileFormat;
+use crate::tileid::TileID;
+use crate::writer::TileWriter;
+
+const HEADER_SIZE: usize = 127;
+
+/// Header and root directory must fit within the first 16 KiB
+const MAX_ROOT_SIZE: usize = 16384 - HEADER_SIZE;
+
+/// Initial number of entries per leaf directory
+const LEAF_SIZE: usize = 4096;
+
+// compression types
+const COMPRESSION_NONE: u8 = 1;
+const COMPRESSION_GZIP: u8 = 2;
+
+/// Entry in a PMTiles directory; run_length is 0 for entries that point to
+/// leaf directories
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+    run_length: u32,
+}
+
+/// Tiles received so far; tile data are written to a temporary file in the
+/// order they are received and only once for identical tile data
+struct State {
+    tile_data: BufWriter<File>,
+    offset: u64,
+    /// (offset, length) of tile data in temporary file, by hash of tile data
+    contents: HashMap<u64, (u64, u32)>,
+    /// (PMTiles tile ID, hash of tile data)
+    tiles: Vec<(u64, u64)>,
+    metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Temporary file that is removed when dropped, including if writing the
+/// archive fails
+struct TempFile {
+    path: PathBuf,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // file is already removed if the archive was finished
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// PMTiles v3 archive writer.  Tiles are written in clustered order (tile data
+/// are ordered by tile ID) when the archive is finished; directories and
+/// metadata are compressed using gzip.
+pub struct PMTiles {
+    path: PathBuf,
+    tile_type: u8,
+    minzoom: u8,
+    maxzoom: u8,
+    bounds: Bounds,
+    state: Mutex<State>,
+    /// declared after state so that the file is closed before it is removed
+    tmp_file: TempFile,
+}
+
+impl PMTiles {
+    pub fn new(
+        path: &PathBuf,
+        format: TileFormat,
+        minzoom: u8,
+        maxzoom: u8,
+        geo_bounds: &Bounds,
+    ) -> Result<PMTiles> {
+        let tmp_path = PathBuf::from(format!("{}.tmp", path.to_str().unwrap()));
+
+        // always overwrite existing file
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+
+        let tile_type = match format {
+            TileFormat::Png => 2u8,
+            TileFormat::Jpeg { .. } => 3u8,
+            TileFormat::WebP => 4u8,
+            // tiles may be either JPEG or PNG, or are data tiles described by
+            // metadata
+            TileFormat::Mixed { .. } | TileFormat::Data => 0u8,
+        };
+
+        Ok(PMTiles {
+            path: path.to_owned(),
+            tile_type,
+            minzoom,
+            maxzoom,
+            bounds: Bounds {
+                xmin: geo_bounds.xmin,
+                ymin: geo_bounds.ymin,
+                xmax: geo_bounds.xmax,
+                ymax: geo_bounds.ymax,
+            },
+            state: Mutex::new(State {
+                tile_data: BufWriter::new(File::create(&tmp_path)?),
+                offset: 0,
+                contents: HashMap::new(),
+                tiles: Vec::new(),
+                metadata: serde_json::Map::new(),
+            }),
+            tmp_file: TempFile { path: tmp_path },
+        })
+    }
+
+    fn header(&self, sections: [u64; 11]) -> Vec<u8> {
+        let mut header: Vec<u8> = Vec::with_capacity(HEADER_SIZE);
+        header.extend(b"PMTiles");
+        header.push(3u8);
+
+        // section offsets and lengths, followed by tile counts
+        for value in sections {
+            header.extend(value.to_le_bytes());
+        }
+
+        header.push(1u8); // clustered
+        header.push(COMPRESSION_GZIP); // internal compression
+        header.push(COMPRESSION_NONE); // tile compression
+        header.push(self.tile_type);
+        header.push(self.minzoom);
+        header.push(self.maxzoom);
+
+        // bounds that cross the antimeridian are written as the full range of
+        // longitudes; the center is between them across the antimeridian
+        let (center_lon, center_lat) = self.bounds.center();
+        let bounds = self.bounds.expand_antimeridian();
+
+        for value in [bounds.xmin, bounds.ymin, bounds.xmax, bounds.ymax] {
+            header.extend(to_e7(value).to_le_bytes());
+        }
+        header.push(self.minzoom);
+        header.extend(to_e7(center_lon).to_le_bytes());
+        header.extend(to_e7(center_lat).to_le_bytes());
+
+        header
+    }
+}
+
+impl TileWriter for PMTiles {
+    fn set_metadata(&self, metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        for &(key, value) in metadata.iter() {
+            state
+                .metadata
+                .insert(key.to_owned(), serde_json::Value::from(value));
+        }
+
+        Ok(())
+    }
+
+    fn write_tile(&self, tile_id: &TileID, tile_data: &[u8]) -> Result<()> {
+        let id = hash(tile_data);
+
+        let mut state = self.state.lock().unwrap();
+        if !state.contents.contains_key(&id) {
+            state.tile_data.write_all(tile_data)?;
+            let offset = state.offset;
+            state.contents.insert(id, (offset, tile_data.len() as u32));
+            state.offset += tile_data.len() as u64;
+        }
+        state.tiles.push((tile_id.hilbert_id(), id));
+
+        Ok(())
+    }
+
+    /// Write the archive from the tiles written to the temporary file, and
+    /// then remove the temporary file
+    fn finish(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.tile_data.flush()?;
+
+        let mut tiles = std::mem::take(&mut state.tiles);
+        tiles.sort_unstable_by_key(|&(tile_id, _)| tile_id);
+
+        // assign offsets in the archive in order of tile ID; runs of
+        // consecutive tiles with identical data are stored as a single entry
+        let mut offsets: HashMap<u64, u64> = HashMap::new();
+        let mut contents: Vec<(u64, u32)> = Vec::new();
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut tile_data_length = 0u64;
+
+        for &(tile_id, id) in tiles.iter() {
+            let (tmp_offset, length) = state.contents[&id];
+            let offset = *offsets.entry(id).or_insert_with(|| {
+                let offset = tile_data_length;
+                contents.push((tmp_offset, length));
+                tile_data_length += length as u64;
+                offset
+            });
+
+            if let Some(last) = entries.last_mut()
+                && last.offset == offset
+                && last.tile_id + last.run_length as u64 == tile_id
+            {
+                last.run_length += 1;
+                continue;
+            }
+
+            entries.push(Entry {
+                tile_id,
+                offset,
+                length,
+                run_length: 1,
+            });
+        }
+
+        let (root, leaves) = build_directories(&entries)?;
+        let metadata = gzip(serde_json::to_string(&state.metadata)?.as_bytes())?;
+
+        let root_offset = HEADER_SIZE as u64;
+        let metadata_offset = root_offset + root.len() as u64;
+        let leaves_offset = metadata_offset + metadata.len() as u64;
+        let tile_data_offset = leaves_offset + leaves.len() as u64;
+
+        let header = self.header([
+            root_offset,
+            root.len() as u64,
+            metadata_offset,
+            metadata.len() as u64,
+            leaves_offset,
+            leaves.len() as u64,
+            tile_data_offset,
+            tile_data_length,
+            tiles.len() as u64,
+            entries.len() as u64,
+            contents.len() as u64,
+        ]);
+
+        let mut out = BufWriter::new(File::create(&self.path)?);
+        out.write_all(&header)?;
+        out.write_all(&root)?;
+        out.write_all(&metadata)?;
+        out.write_all(&leaves)?;
+
+        // copy tile data from temporary file in order of tile ID
+        let mut tmp = File::open(&self.tmp_file.path)?;
+        let mut buffer: Vec<u8> = Vec::new();
+        for (tmp_offset, length) in contents {
+            buffer.resize(length as usize, 0u8);
+            tmp.seek(SeekFrom::Start(tmp_offset))?;
+            tmp.read_exact(&mut buffer)?;
+            out.write_all(&buffer)?;
+        }
+        out.flush()?;
+
+        fs::remove_file(&self.tmp_file.path)?;
+
+        Ok(())
+    }
+}
+
+/// Convert coordinate to integer degrees * 10^7
+fn to_e7(value: f64) -> i32 {
+    (value * 1e7).round() as i32
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Serialize and compress directory entries, which must be sorted by tile ID
+fn serialize_directory(entries: &[Entry]) -> Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_varint(&mut buffer, entries.len() as u64);
+
+    // tile IDs are delta encoded
+    let mut last_id = 0u64;
+    for entry in entries {
+        write_varint(&mut buffer, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+
+    for entry in entries {
+        write_varint(&mut buffer, entry.run_length as u64);
+    }
+
+    for entry in entries {
+        write_varint(&mut buffer, entry.length as u64);
+    }
+
+    // offsets that immediately follow the previous entry are encoded as 0,
+    // otherwise as offset + 1
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 && entry.offset == entries[i - 1].offset + entries[i - 1].length as u64 {
+            write_varint(&mut buffer, 0);
+        } else {
+            write_varint(&mut buffer, entry.offset + 1);
+        }
+    }
+
+    gzip(&buffer)
+}
+
+/// Build root directory and leaf directories (if needed so that the root
+/// directory fits within the first 16 KiB of the archive)
+fn build_directories(entries: &[Entry]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let root = serialize_directory(entries)?;
+    if root.len() <= MAX_ROOT_SIZE {
+        return Ok((root, Vec::new()));
+    }
+
+    let mut leaf_size = LEAF_SIZE;
+    loop {
+        let mut root_entries: Vec<Entry> = Vec::new();
+        let mut leaves: Vec<u8> = Vec::new();
+
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = serialize_directory(chunk)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend(leaf);
+        }
+
+        let root = serialize_directory(&root_entries)?;
+        if root.len() <= MAX_ROOT_SIZE {
+            return Ok((root, leaves));
+        }
+
+        leaf_size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use rstest::rstest;
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn read_u64(data: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[rstest]
+    #[case(0, vec![0x00])]
+    #[case(127, vec![0x7F])]
+    #[case(128, vec![0x80, 0x01])]
+    #[case(300, vec![0xAC, 0x02])]
+    fn varint(#[case] value: u64, #[case] expected: Vec<u8>) {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_varint(&mut buffer, value);
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn directory() {
+        let entries = [
+            Entry {
+                tile_id: 0,
+                offset: 0,
+                length: 10,
+                run_length: 1,
+            },
+            Entry {
+                tile_id: 1,
+                offset: 10,
+                length: 5,
+                run_length: 3,
+            },
+            Entry {
+                tile_id: 5,
+                offset: 0,
+                length: 10,
+                run_length: 1,
+            },
+        ];
+        let directory = gunzip(&serialize_directory(&entries).unwrap());
+        assert_eq!(directory, [3, 0, 1, 4, 1, 3, 1, 10, 5, 10, 1, 0, 1]);
+    }
+
+    #[test]
+    fn leaf_directories() {
+        // use varying offsets and lengths so that entries do not compress well
+        let entries: Vec<Entry> = (0..100_000u64)
+            .map(|i| Entry {
+                tile_id: i * 2,
+                offset: hash(&i.to_le_bytes()) >> 24,
+                length: (hash(&i.to_be_bytes()) >> 48) as u32,
+                run_length: 1,
+            })
+            .collect();
+
+        let (root, leaves) = build_directories(&entries).unwrap();
+        assert!(root.len() <= MAX_ROOT_SIZE);
+        assert!(!leaves.is_empty());
+    }
+
+    #[test]
+    fn write_archive() {
+        let path = std::env::temp_dir().join("rastertiler_write_archive.pmtiles");
+        let bounds = Bounds {
+            xmin: -180.,
+            ymin: -85.,
+            xmax: 180.,
+            ymax: 85.,
+        };
+        let pmtiles = PMTiles::new(&path, TileFormat::Png, 0, 1, &bounds).unwrap();
+        pmtiles.set_metadata(&[("name", "test")]).unwrap();
+
+        // tiles are received out of order; 2 tiles share the same data
+        pmtiles.write_tile(&TileID::new(1, 1, 0), b"c").unwrap();
+        pmtiles.write_tile(&TileID::new(1, 0, 0), b"bb").unwrap();
+        pmtiles.write_tile(&TileID::new(1, 0, 1), b"bb").unwrap();
+        pmtiles.write_tile(&TileID::new(0, 0, 0), b"aaa").unwrap();
+        pmtiles.finish().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(&data[..8], b"PMTiles\x03");
+        assert_eq!(read_u64(&data, 8), HEADER_SIZE as u64);
+        // addressed tiles, tile entries, tile contents
+        assert_eq!(read_u64(&data, 72), 4);
+        assert_eq!(read_u64(&data, 80), 3);
+        assert_eq!(read_u64(&data, 88), 3);
+        // clustered, internal compression, tile compression, tile type, zooms
+        assert_eq!(data[96..102], [1, 2, 1, 2, 0, 1]);
+
+        let metadata_offset = read_u64(&data, 24) as usize;
+        let metadata_length = read_u64(&data, 32) as usize;
+        let metadata = gunzip(&data[metadata_offset..metadata_offset + metadata_length]);
+        assert_eq!(metadata, br#"{"name":"test"}"#);
+
+        // tile data are ordered by tile ID
+        let tile_data_offset = read_u64(&data, 56) as usize;
+        assert_eq!(&data[tile_data_offset..], b"aaabbc");
+    }
+
+    #[test]
+    fn header_antimeridian() {
+        let path = std::env::temp_dir().join("rastertiler_header_antimeridian.pmtiles");
+        let bounds = Bounds {
+            xmin: 170.,
+            ymin: -10.,
+            xmax: -160.,
+            ymax: 10.,
+        };
+        let pmtiles = PMTiles::new(&path, TileFormat::Png, 0, 1, &bounds).unwrap();
+        let header = pmtiles.header([0; 11]);
+        drop(pmtiles);
+
+        let read_i32 =
+            |offset: usize| i32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+
+        // bounds have the full range of longitudes
+        assert_eq!(
+            [read_i32(102), read_i32(106), read_i32(110), read_i32(114)],
+            [-1_800_000_000, -100_000_000, 1_800_000_000, 100_000_000]
+        );
+        // center is across the antimeridian
+        assert_eq!([read_i32(119), read_i32(123)], [-1_750_000_000, 0]);
+    }
+
+    #[test]
+    fn remove_tmp_file_on_drop() {
+        let path = std::env::temp_dir().join("rastertiler_remove_tmp_file.pmtiles");
+        let tmp_path = PathBuf::from(format!("{}.tmp", path.to_str().unwrap()));
+        let bounds = Bounds {
+            xmin: -180.,
+            ymin: -85.,
+            xmax: 180.,
+            ymax: 85.,
+        };
+
+        let pmtiles = PMTiles::new(&path, TileFormat::Png, 0, 1, &bounds).unwrap();
+        pmtiles.write_tile(&TileID::new(0, 0, 0), b"aaa").unwrap();
+        assert!(tmp_path.exists());
+
+        // archive is not finished, e.g., because rendering failed
+        drop(pmtiles);
+        assert!(!tmp_path.exists());
+        assert!(!path.exists());
+    }
+}
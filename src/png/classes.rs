@@ -0,0 +1,172 @@
+use std::error::Error;
+
+use crate::png::color::{ColormapRgb8, Rgb8};
+
+/// Edge of each class range that is inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassEdges {
+    /// Classes include their lower edge: min <= value < max
+    Lower,
+    /// Classes include their upper edge: min < value <= max
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Class {
+    min: f64,
+    max: f64,
+    color: Rgb8,
+    alpha: u8,
+}
+
+/// Colormap that bins continuous values into classes defined by ranges of
+/// values.  Values are classified to the first class whose range contains
+/// them; values not in any class are transparent.
+#[derive(Debug)]
+pub struct ClassifiedColormap {
+    classes: Vec<Class>,
+    edges: ClassEdges,
+}
+
+impl ClassifiedColormap {
+    /// Parse classes from comma-delimited range:hex color pairs, e.g.,
+    /// "0-10:#hex,10-25:#hex,25+:#hex".  Ranges are "<min>-<max>",
+    /// "<min>+" (no upper limit), or "<<max>" (no lower limit); colors may
+    /// include alpha as "#RRGGBBAA".
+    pub fn parse(
+        classes_str: &str,
+        edges: ClassEdges,
+    ) -> Result<ClassifiedColormap, Box<dyn Error>> {
+        let mut classes: Vec<Class> = Vec::new();
+
+        for entry in classes_str.split(',') {
+            let (range, color) = entry
+                .split_once(':')
+                .ok_or(format!("invalid class: {}", entry))?;
+            let (min, max) =
+                parse_range(range.trim()).ok_or(format!("invalid class range: {}", range))?;
+
+            if min >= max {
+                return Err(format!("class minimum must be less than maximum: {}", range).into());
+            }
+
+            let (color, alpha) = Rgb8::from_hex_alpha(color.trim())?;
+            classes.push(Class {
+                min,
+                max,
+                color,
+                alpha,
+            });
+        }
+
+        // index 0 is reserved for NODATA
+        if classes.len() > 255 {
+            return Err("classes must have no more than 255 entries")?;
+        }
+
+        Ok(ClassifiedColormap { classes, edges })
+    }
+
+    /// Return index of class (1-based) that contains value, returning index 0
+    /// if not found (corresponds to transparent)
+    pub fn classify(&self, value: f64) -> u8 {
+        for (i, class) in self.classes.iter().enumerate() {
+            let contains = match self.edges {
+                ClassEdges::Lower => value >= class.min && value < class.max,
+                ClassEdges::Upper => value > class.min && value <= class.max,
+            };
+            if contains {
+                return i as u8 + 1;
+            }
+        }
+        0u8
+    }
+
+    /// Create colormap where index 0 is reserved for NODATA and indexes
+    /// 1..n are the colors of each class.  Values must be classified using
+    /// classify() before encoding with this colormap.
+    pub fn colormap(&self) -> ColormapRgb8<u8> {
+        let mut colormap = ColormapRgb8::<u8>::new(self.classes.len(), 0u8);
+        for (i, class) in self.classes.iter().enumerate() {
+            colormap.add_color_alpha(i as u8 + 1, class.color, class.alpha);
+        }
+        colormap
+    }
+}
+
+/// Parse range as "<min>-<max>", "<min>+", or "<<max>"; open ends are infinite
+fn parse_range(range: &str) -> Option<(f64, f64)> {
+    if let Some(min) = range.strip_suffix('+') {
+        return Some((min.parse().ok()?, f64::INFINITY));
+    }
+
+    if let Some(max) = range.strip_prefix('<') {
+        return Some((f64::NEG_INFINITY, max.parse().ok()?));
+    }
+
+    // the separator is the first hyphen that follows a digit or decimal point,
+    // so that negative values and exponents are parsed correctly, e.g., "-10--5"
+    let bytes = range.as_bytes();
+    let index = (1..bytes.len())
+        .find(|&i| bytes[i] == b'-' && (bytes[i - 1].is_ascii_digit() || bytes[i - 1] == b'.'))?;
+
+    Some((
+        range[..index].parse().ok()?,
+        range[index + 1..].parse().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("0-10", Some((0., 10.)))]
+    #[case("-10--5", Some((-10., -5.)))]
+    #[case("1e-5-2.5", Some((1e-5, 2.5)))]
+    #[case("25+", Some((25., f64::INFINITY)))]
+    #[case("<-1", Some((f64::NEG_INFINITY, -1.)))]
+    #[case("10", None)]
+    #[case("a-b", None)]
+    fn test_parse_range(#[case] range: &str, #[case] expected: Option<(f64, f64)>) {
+        assert_eq!(parse_range(range), expected);
+    }
+
+    #[rstest]
+    #[case("0-10")]
+    #[case("0:#FF0000")]
+    #[case("10-0:#FF0000")]
+    #[case("0-10:#FF0000,10-20:#FFF")]
+    fn test_parse_invalid(#[case] classes_str: &str) {
+        assert!(ClassifiedColormap::parse(classes_str, ClassEdges::Lower).is_err());
+    }
+
+    #[rstest]
+    #[case(ClassEdges::Lower, -1., 0u8)]
+    #[case(ClassEdges::Lower, 0., 1u8)]
+    #[case(ClassEdges::Lower, 10., 2u8)]
+    #[case(ClassEdges::Lower, 25., 3u8)]
+    #[case(ClassEdges::Lower, 1000., 3u8)]
+    #[case(ClassEdges::Lower, f64::NAN, 0u8)]
+    #[case(ClassEdges::Upper, 0., 0u8)]
+    #[case(ClassEdges::Upper, 10., 1u8)]
+    #[case(ClassEdges::Upper, 25., 2u8)]
+    #[case(ClassEdges::Upper, 25.1, 3u8)]
+    fn test_classify(#[case] edges: ClassEdges, #[case] value: f64, #[case] expected: u8) {
+        let classes =
+            ClassifiedColormap::parse("0-10:#FF0000,10-25:#00FF00,25+:#0000FF", edges).unwrap();
+        assert_eq!(classes.classify(value), expected);
+    }
+
+    #[test]
+    fn test_colormap() {
+        let classes =
+            ClassifiedColormap::parse("0-10:#FF0000,10+:#0000FF80", ClassEdges::Lower).unwrap();
+        let colormap = classes.colormap();
+        assert_eq!(colormap.len(), 3);
+        assert_eq!(colormap.get_index(2), 2);
+        assert_eq!(colormap.get_colors()[3..9], [255, 0, 0, 0, 0, 255]);
+        assert_eq!(colormap.get_transparency(), [0u8, 255u8, 128u8]);
+    }
+}
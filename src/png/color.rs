@@ -1,9 +1,9 @@
 use std::collections::BTreeMap;
 use std::error::Error;
 
-use crate::png::PixelValue;
+use crate::png::{multiply_alpha, PixelValue};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Rgb8 {
     pub r: u8,
     pub g: u8,
@@ -12,7 +12,8 @@ pub struct Rgb8 {
 
 impl Rgb8 {
     pub fn from_hex(hex_str: &str) -> Result<Rgb8, Box<dyn Error>> {
-        if hex_str.len() != 7 {
+        // check for ASCII so that slicing by byte does not split a character
+        if hex_str.len() != 7 || !hex_str.is_ascii() {
             return Err("unsupported hex format")?;
         }
 
@@ -25,6 +26,24 @@ impl Rgb8 {
         })
     }
 
+    /// Parse a hex color with optional alpha, as "#RRGGBB" or "#RRGGBBAA";
+    /// alpha is 255 (opaque) if not provided
+    pub fn from_hex_alpha(hex_str: &str) -> Result<(Rgb8, u8), Box<dyn Error>> {
+        if !hex_str.is_ascii() {
+            return Err("unsupported hex format")?;
+        }
+
+        match hex_str.len() {
+            7 => Ok((Rgb8::from_hex(hex_str)?, 255u8)),
+            9 => {
+                let color = Rgb8::from_hex(&hex_str[..7])?;
+                let alpha = hex::decode(&hex_str[7..])?;
+                Ok((color, alpha[0]))
+            }
+            _ => Err("unsupported hex format")?,
+        }
+    }
+
     pub fn from_u32(value: u32) -> Rgb8 {
         Rgb8 {
             r: (value >> 16u32) as u8,
@@ -32,12 +51,235 @@ impl Rgb8 {
             b: (value & 0xFF) as u8,
         }
     }
+
+    /// Convert sRGB color to CIE L*a*b* (D65 white point)
+    pub fn to_lab(self) -> [f64; 3] {
+        let r = srgb_to_linear(self.r);
+        let g = srgb_to_linear(self.g);
+        let b = srgb_to_linear(self.b);
+
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / WHITE_D65[0];
+        let y = (0.2126729 * r + 0.7151522 * g + 0.0721750 * b) / WHITE_D65[1];
+        let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / WHITE_D65[2];
+
+        let (fx, fy, fz) = (lab_f(x), lab_f(y), lab_f(z));
+        [116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz)]
+    }
+
+    /// Convert CIE L*a*b* (D65 white point) to sRGB color, clamping colors
+    /// outside the sRGB gamut
+    pub fn from_lab(lab: [f64; 3]) -> Rgb8 {
+        let fy = (lab[0] + 16.) / 116.;
+        let fx = fy + lab[1] / 500.;
+        let fz = fy - lab[2] / 200.;
+
+        let x = lab_f_inv(fx) * WHITE_D65[0];
+        let y = lab_f_inv(fy) * WHITE_D65[1];
+        let z = lab_f_inv(fz) * WHITE_D65[2];
+
+        Rgb8 {
+            r: linear_to_srgb(3.2404542 * x - 1.5371385 * y - 0.4985314 * z),
+            g: linear_to_srgb(-0.9692660 * x + 1.8760108 * y + 0.0415560 * z),
+            b: linear_to_srgb(0.0556434 * x - 0.2040259 * y + 1.0572252 * z),
+        }
+    }
 }
 
-#[derive(Debug)]
+const WHITE_D65: [f64; 3] = [0.95047, 1., 1.08883];
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u8 {
+    let v = if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    };
+    (v * 255.).round().clamp(0., 255.) as u8
+}
+
+fn lab_f(t: f64) -> f64 {
+    if t > 216. / 24389. {
+        t.cbrt()
+    } else {
+        (24389. / 27. * t + 16.) / 116.
+    }
+}
+
+fn lab_f_inv(t: f64) -> f64 {
+    if t.powi(3) > 216. / 24389. {
+        t.powi(3)
+    } else {
+        (116. * t - 16.) * 27. / 24389.
+    }
+}
+
+/// Built-in named palettes as colors sampled at equal intervals; these are
+/// the perceptually uniform matplotlib palettes and ColorBrewer sequential
+/// and diverging palettes
+const NAMED_PALETTES: [(&str, &[&str]); 15] = [
+    (
+        "viridis",
+        &[
+            "#440154", "#482878", "#3e4989", "#31688e", "#26828e", "#1f9e89", "#35b779", "#6ece58",
+            "#b5de2b", "#fde725",
+        ],
+    ),
+    (
+        "magma",
+        &[
+            "#000004", "#180f3d", "#440f76", "#721f81", "#9e2f7f", "#cd4071", "#f1605d", "#fd9668",
+            "#feca8d", "#fcfdbf",
+        ],
+    ),
+    (
+        "inferno",
+        &[
+            "#000004", "#1b0c41", "#4a0c6b", "#781c6d", "#a52c60", "#cf4446", "#ed6925", "#fb9b06",
+            "#f7d13d", "#fcffa4",
+        ],
+    ),
+    (
+        "plasma",
+        &[
+            "#0d0887", "#46039f", "#7201a8", "#9c179e", "#bd3786", "#d8576b", "#ed7953", "#fb9f3a",
+            "#fdca26", "#f0f921",
+        ],
+    ),
+    (
+        "cividis",
+        &[
+            "#00224e", "#123570", "#3b496c", "#575d6d", "#707173", "#8a8779", "#a69d75", "#c4b56c",
+            "#e4cf5b", "#fee838",
+        ],
+    ),
+    (
+        "blues",
+        &[
+            "#f7fbff", "#deebf7", "#c6dbef", "#9ecae1", "#6baed6", "#4292c6", "#2171b5", "#08519c",
+            "#08306b",
+        ],
+    ),
+    (
+        "greens",
+        &[
+            "#f7fcf5", "#e5f5e0", "#c7e9c0", "#a1d99b", "#74c476", "#41ab5d", "#238b45", "#006d2c",
+            "#00441b",
+        ],
+    ),
+    (
+        "greys",
+        &[
+            "#ffffff", "#f0f0f0", "#d9d9d9", "#bdbdbd", "#969696", "#737373", "#525252", "#252525",
+            "#000000",
+        ],
+    ),
+    (
+        "reds",
+        &[
+            "#fff5f0", "#fee0d2", "#fcbba1", "#fc9272", "#fb6a4a", "#ef3b2c", "#cb181d", "#a50f15",
+            "#67000d",
+        ],
+    ),
+    (
+        "ylgnbu",
+        &[
+            "#ffffd9", "#edf8b1", "#c7e9b4", "#7fcdbb", "#41b6c4", "#1d91c0", "#225ea8", "#253494",
+            "#081d58",
+        ],
+    ),
+    (
+        "ylorrd",
+        &[
+            "#ffffcc", "#ffeda0", "#fed976", "#feb24c", "#fd8d3c", "#fc4e2a", "#e31a1c", "#bd0026",
+            "#800026",
+        ],
+    ),
+    (
+        "rdbu",
+        &[
+            "#67001f", "#b2182b", "#d6604d", "#f4a582", "#fddbc7", "#f7f7f7", "#d1e5f0", "#92c5de",
+            "#4393c3", "#2166ac", "#053061",
+        ],
+    ),
+    (
+        "rdylbu",
+        &[
+            "#a50026", "#d73027", "#f46d43", "#fdae61", "#fee090", "#ffffbf", "#e0f3f8", "#abd9e9",
+            "#74add1", "#4575b4", "#313695",
+        ],
+    ),
+    (
+        "rdylgn",
+        &[
+            "#a50026", "#d73027", "#f46d43", "#fdae61", "#fee08b", "#ffffbf", "#d9ef8b", "#a6d96a",
+            "#66bd63", "#1a9850", "#006837",
+        ],
+    ),
+    (
+        "spectral",
+        &[
+            "#9e0142", "#d53e4f", "#f46d43", "#fdae61", "#fee08b", "#ffffbf", "#e6f598", "#abdda4",
+            "#66c2a5", "#3288bd", "#5e4fa2",
+        ],
+    ),
+];
+
+/// Return colors of a built-in named palette (case-insensitive), if found
+pub fn named_palette(name: &str) -> Option<Vec<Rgb8>> {
+    let name = name.to_lowercase();
+    let (_, colors) = NAMED_PALETTES.iter().find(|(n, _)| *n == name)?;
+
+    Some(
+        colors
+            .iter()
+            .map(|c| Rgb8::from_hex(c).expect("built-in palette colors are valid"))
+            .collect(),
+    )
+}
+
+/// Colormap entry as parsed from a colormap string or file; the value is
+/// parsed to the data type of the colormap when the colormap is created
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColormapEntry {
+    pub value: String,
+    pub color: Rgb8,
+    pub alpha: u8,
+}
+
+/// Parse colormap entries from comma-delmited value:hex color pairs, e.g.,
+/// "<value>:<hex>,<value:hex>"; colors may include alpha as "#RRGGBBAA"
+pub fn parse_colormap(colormap_str: &str) -> Result<Vec<ColormapEntry>, Box<dyn Error>> {
+    let mut entries: Vec<ColormapEntry> = Vec::new();
+
+    for entry in colormap_str.split(',') {
+        let (value, color) = entry
+            .split_once(':')
+            .ok_or(format!("invalid colormap entry: {}", entry))?;
+
+        let (color, alpha) = Rgb8::from_hex_alpha(color.trim())?;
+        entries.push(ColormapEntry {
+            value: value.trim().to_string(),
+            color,
+            alpha,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[derive(Debug, Clone)]
 pub struct ColormapRgb8<T: PixelValue> {
     values: BTreeMap<T, u8>,
     colors: Vec<u8>,
+    alpha: Vec<u8>,
     nodata: T,
 }
 
@@ -46,6 +288,7 @@ impl<T: PixelValue> ColormapRgb8<T> {
         let mut colormap = ColormapRgb8 {
             values: BTreeMap::new(),
             colors: Vec::with_capacity((capacity + 1) * 3),
+            alpha: Vec::with_capacity(capacity + 1),
             nodata,
         };
 
@@ -54,6 +297,7 @@ impl<T: PixelValue> ColormapRgb8<T> {
         colormap.colors.push(0u8);
         colormap.colors.push(0u8);
         colormap.colors.push(0u8);
+        colormap.alpha.push(0u8);
 
         colormap
     }
@@ -61,15 +305,21 @@ impl<T: PixelValue> ColormapRgb8<T> {
     pub fn clear(&mut self) {
         self.values.clear();
         self.colors.clear();
+        self.alpha.clear();
 
         // add back in NODATA
         self.values.insert(self.nodata, 0u8);
         self.colors.push(0u8);
         self.colors.push(0u8);
         self.colors.push(0u8);
+        self.alpha.push(0u8);
     }
 
     pub fn add_color(&mut self, value: T, color: Rgb8) {
+        self.add_color_alpha(value, color, 255u8);
+    }
+
+    pub fn add_color_alpha(&mut self, value: T, color: Rgb8, alpha: u8) {
         // only add unique entries
         #![allow(clippy::map_entry)]
         if !self.values.contains_key(&value) {
@@ -77,25 +327,40 @@ impl<T: PixelValue> ColormapRgb8<T> {
             self.colors.push(color.r);
             self.colors.push(color.g);
             self.colors.push(color.b);
+            self.alpha.push(alpha);
         }
     }
 
-    pub fn parse(colormap_str: &str, nodata: u8) -> Result<ColormapRgb8<u8>, Box<dyn Error>> {
-        let num_colors = colormap_str.matches(',').count() + 1;
-        let mut colormap = ColormapRgb8::<u8>::new(num_colors, nodata);
+    pub fn from_entries(
+        entries: &[ColormapEntry],
+        nodata: T,
+    ) -> Result<ColormapRgb8<T>, Box<dyn Error>> {
+        // index 0 is reserved for NODATA
+        if entries.len() > 255 {
+            return Err("colormap must have no more than 255 entries")?;
+        }
+
+        let mut colormap = ColormapRgb8::<T>::new(entries.len(), nodata);
 
-        let mut value: u8;
-        let mut color: Rgb8;
-        for entry in colormap_str.split(',') {
-            let parts: Vec<&str> = entry.split(':').collect();
-            value = parts[0].parse()?;
-            color = Rgb8::from_hex(parts[1])?;
-            colormap.add_color(value, color);
+        let mut value: T;
+        for entry in entries.iter() {
+            value = entry
+                .value
+                .parse()
+                .map_err(|_| format!("invalid colormap value: {}", entry.value))?;
+            colormap.add_color_alpha(value, entry.color, entry.alpha);
         }
 
         Ok(colormap)
     }
 
+    /// Multiply alpha of all entries except NODATA by opacity
+    pub fn apply_opacity(&mut self, opacity: u8) {
+        for alpha in self.alpha.iter_mut().skip(1) {
+            *alpha = multiply_alpha(*alpha, opacity);
+        }
+    }
+
     /// Return index value for input value, returning index 0
     /// if not found (corresponds to transparent)
     pub fn get_index(&self, value: T) -> u8 {
@@ -110,8 +375,11 @@ impl<T: PixelValue> ColormapRgb8<T> {
     }
 
     pub fn get_transparency(&self) -> &[u8] {
-        // transparency is always stored in lowest index
-        &[0u8][..]
+        // transparency of NODATA is always stored in lowest index; entries
+        // after the last transparent entry are omitted because they default
+        // to opaque
+        let len = self.alpha.iter().rposition(|&a| a < 255).unwrap_or(0) + 1;
+        &self.alpha[..len]
     }
 
     pub fn len(&self) -> usize {
@@ -130,4 +398,145 @@ mod tests {
         let actual = Rgb8::from_hex(hex_str).expect("color not parsed correctly");
         assert_eq!(actual, expected);
     }
+
+    #[rstest]
+    #[case("#FF00FF", Rgb8{r: 255u8, g: 0u8, b: 255u8}, 255u8)]
+    #[case("#FF00FF80", Rgb8{r: 255u8, g: 0u8, b: 255u8}, 128u8)]
+    fn test_color_from_hex_alpha(
+        #[case] hex_str: &str,
+        #[case] expected_color: Rgb8,
+        #[case] expected_alpha: u8,
+    ) {
+        let (color, alpha) = Rgb8::from_hex_alpha(hex_str).expect("color not parsed correctly");
+        assert_eq!(color, expected_color);
+        assert_eq!(alpha, expected_alpha);
+    }
+
+    #[rstest]
+    #[case("#FF00FF8")]
+    #[case("#FF00FFGG")]
+    #[case("#FF00F\u{e9}F")]
+    #[case("\u{e9}FF00F")]
+    fn test_color_from_hex_alpha_invalid(#[case] hex_str: &str) {
+        assert!(Rgb8::from_hex_alpha(hex_str).is_err());
+    }
+
+    #[rstest]
+    #[case(Rgb8{r: 0, g: 0, b: 0}, [0., 0., 0.])]
+    #[case(Rgb8{r: 255, g: 255, b: 255}, [100., 0., 0.])]
+    #[case(Rgb8{r: 255, g: 0, b: 0}, [53.24, 80.09, 67.20])]
+    fn test_color_lab(#[case] color: Rgb8, #[case] expected: [f64; 3]) {
+        let lab = color.to_lab();
+        for i in 0..3 {
+            assert!((lab[i] - expected[i]).abs() < 0.01, "{:?}", lab);
+        }
+        assert_eq!(Rgb8::from_lab(lab), color);
+    }
+
+    #[test]
+    fn test_named_palette() {
+        let colors = named_palette("Viridis").unwrap();
+        assert_eq!(colors.len(), 10);
+        assert_eq!(
+            colors[0],
+            Rgb8 {
+                r: 0x44,
+                g: 0x01,
+                b: 0x54
+            }
+        );
+
+        for (name, _) in NAMED_PALETTES.iter() {
+            assert!(named_palette(name).unwrap().len() >= 9);
+        }
+
+        assert!(named_palette("unknown").is_none());
+    }
+
+    #[test]
+    fn test_parse_colormap() {
+        let entries = parse_colormap("1:#686868, 2:#fbb4b9").unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ColormapEntry {
+                    value: String::from("1"),
+                    color: Rgb8 {
+                        r: 104,
+                        g: 104,
+                        b: 104
+                    },
+                    alpha: 255
+                },
+                ColormapEntry {
+                    value: String::from("2"),
+                    color: Rgb8 {
+                        r: 251,
+                        g: 180,
+                        b: 185
+                    },
+                    alpha: 255
+                }
+            ]
+        );
+
+        let colormap = ColormapRgb8::<u8>::from_entries(&entries, 0).unwrap();
+        assert_eq!(colormap.len(), 3);
+        assert_eq!(colormap.get_index(2), 2);
+        assert_eq!(colormap.get_index(3), 0);
+    }
+
+    #[rstest]
+    #[case("1")]
+    #[case("1:#686868,2")]
+    #[case("1:686868")]
+    fn test_parse_colormap_invalid(#[case] colormap_str: &str) {
+        assert!(parse_colormap(colormap_str).is_err());
+    }
+
+    #[test]
+    fn test_colormap_u16() {
+        let entries = parse_colormap("1:#686868,1000:#fbb4b9").unwrap();
+        let colormap = ColormapRgb8::<u16>::from_entries(&entries, 65535).unwrap();
+        assert_eq!(colormap.len(), 3);
+        assert_eq!(colormap.get_index(1000), 2);
+        assert_eq!(colormap.get_index(65535), 0);
+    }
+
+    #[test]
+    fn test_colormap_invalid_value() {
+        let entries = parse_colormap("256:#686868").unwrap();
+        assert!(ColormapRgb8::<u8>::from_entries(&entries, 0).is_err());
+        assert!(ColormapRgb8::<u16>::from_entries(&entries, 0).is_ok());
+
+        let entries = parse_colormap("-1:#686868").unwrap();
+        assert!(ColormapRgb8::<u32>::from_entries(&entries, 0).is_err());
+        assert!(ColormapRgb8::<i16>::from_entries(&entries, 0).is_ok());
+    }
+
+    #[test]
+    fn test_colormap_transparency() {
+        let mut colormap = ColormapRgb8::<u8>::new(3, 255u8);
+        assert_eq!(colormap.get_transparency(), [0u8]);
+
+        colormap.add_color(1, Rgb8 { r: 255, g: 0, b: 0 });
+        colormap.add_color_alpha(2, Rgb8 { r: 0, g: 255, b: 0 }, 128);
+        colormap.add_color(3, Rgb8 { r: 0, g: 0, b: 255 });
+        assert_eq!(colormap.get_transparency(), [0u8, 255u8, 128u8]);
+
+        colormap.clear();
+        assert_eq!(colormap.get_transparency(), [0u8]);
+    }
+
+    #[test]
+    fn test_colormap_alpha() {
+        let entries = parse_colormap("1:#686868,2:#fbb4b980,3:#c51b8a").unwrap();
+        assert_eq!(entries[1].alpha, 128);
+
+        let mut colormap = ColormapRgb8::<u8>::from_entries(&entries, 0).unwrap();
+        assert_eq!(colormap.get_transparency(), [0u8, 255u8, 128u8]);
+
+        colormap.apply_opacity(128);
+        assert_eq!(colormap.get_transparency(), [0u8, 128u8, 64u8, 128u8]);
+    }
 }
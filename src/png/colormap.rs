@@ -28,19 +28,6 @@ impl<T: PixelValue> ColormapEncoder<T> {
         })
     }
 
-    pub fn from_str(
-        width: u32,
-        height: u32,
-        colormap_str: &str,
-        nodata: u8,
-    ) -> Result<ColormapEncoder<u8>, Box<dyn Error>> {
-        Ok(ColormapEncoder {
-            width,
-            height,
-            colormap: ColormapRgb8::<u8>::parse(colormap_str, nodata)?,
-        })
-    }
-
     fn pack_1bit(&self, buffer: &[T]) -> Vec<u8> {
         let mut pixels: Vec<u8> = Vec::with_capacity(buffer.len() / 8);
         for i in (0..buffer.len()).step_by(8) {
@@ -0,0 +1,317 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::Value;
+
+use crate::png::color::{ColormapEntry, Rgb8};
+
+/// Read colormap entries from a file, based on its extension:
+/// * `.txt`: GDAL color-relief text file (`gdaldem color-relief`)
+/// * `.clr`: QGIS exported color map or ArcGIS colormap
+/// * `.qml`: QGIS style file with a paletted or pseudocolor renderer
+/// * `.json`: array of `{"value": <value>, "color": "<hex>"}` objects
+pub fn read_colormap_file(path: &Path) -> Result<Vec<ColormapEntry>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let entries = match extension.as_str() {
+        "txt" => parse_color_relief(&contents)?,
+        "clr" => parse_clr(&contents)?,
+        "qml" => parse_qml(&contents)?,
+        "json" => parse_json(&contents)?,
+        _ => {
+            return Err(format!(
+                "unsupported colormap file extension: {:?}; must be one of .txt, .clr, .qml, .json",
+                extension
+            )
+            .into());
+        }
+    };
+
+    if entries.is_empty() {
+        return Err("colormap file does not contain any entries".into());
+    }
+
+    Ok(entries)
+}
+
+/// Parse a color component, clamping to 0-255
+fn parse_component(value: &str) -> Result<u8, Box<dyn Error>> {
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("invalid color component: {}", value))?;
+    Ok(value.round().clamp(0., 255.) as u8)
+}
+
+/// Parse entries from a GDAL color-relief text file, where each line is
+/// "<value> <R> <G> <B> [<A>]" and values are delimited by whitespace, commas,
+/// tabs, or colons.  NODATA ("nv") entries are ignored because NODATA is always
+/// transparent.
+pub fn parse_color_relief(contents: &str) -> Result<Vec<ColormapEntry>, Box<dyn Error>> {
+    let mut entries: Vec<ColormapEntry> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ':')
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        if parts[0] == "nv" {
+            continue;
+        }
+
+        if parts[0].ends_with('%') {
+            return Err(
+                format!("percentage values are not supported in colormap: {}", line).into(),
+            );
+        }
+
+        if parts.len() < 4 {
+            return Err(format!("invalid colormap entry: {}", line).into());
+        }
+
+        entries.push(ColormapEntry {
+            value: parts[0].to_string(),
+            color: Rgb8 {
+                r: parse_component(parts[1])?,
+                g: parse_component(parts[2])?,
+                b: parse_component(parts[3])?,
+            },
+            alpha: match parts.get(4) {
+                Some(a) => parse_component(a)?,
+                None => 255u8,
+            },
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Parse entries from a QGIS exported color map, where each line is
+/// "<value>,<R>,<G>,<B>,<A>,<label>", or an ArcGIS colormap, where each line
+/// is "<value> <R> <G> <B>"
+pub fn parse_clr(contents: &str) -> Result<Vec<ColormapEntry>, Box<dyn Error>> {
+    let mut entries: Vec<ColormapEntry> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("INTERPOLATION:") {
+            continue;
+        }
+
+        // QGIS exports are comma-delimited and may contain spaces in labels
+        let parts: Vec<&str> = if line.contains(',') {
+            line.split(',').map(|p| p.trim()).collect()
+        } else {
+            line.split_whitespace().collect()
+        };
+
+        if parts.len() < 4 {
+            return Err(format!("invalid colormap entry: {}", line).into());
+        }
+
+        entries.push(ColormapEntry {
+            value: parts[0].to_string(),
+            color: Rgb8 {
+                r: parse_component(parts[1])?,
+                g: parse_component(parts[2])?,
+                b: parse_component(parts[3])?,
+            },
+            // 5th column is alpha if numeric, otherwise start of label
+            alpha: match parts.get(4).map(|a| a.parse::<u8>()) {
+                Some(Ok(a)) => a,
+                _ => 255u8,
+            },
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Parse entries from a QGIS style file, using `paletteEntry` elements of
+/// a paletted renderer or `item` elements of a pseudocolor renderer
+pub fn parse_qml(contents: &str) -> Result<Vec<ColormapEntry>, Box<dyn Error>> {
+    let mut entries: Vec<ColormapEntry> = Vec::new();
+    let mut reader = Reader::from_str(contents);
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e)
+                if matches!(e.name().as_ref(), b"paletteEntry" | b"item") =>
+            {
+                let mut value: Option<String> = None;
+                let mut color: Option<Rgb8> = None;
+                let mut alpha = 255u8;
+
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    let attr_value = attr.unescape_value()?;
+                    match attr.key.as_ref() {
+                        b"value" => value = Some(attr_value.to_string()),
+                        b"color" => color = Some(Rgb8::from_hex(&attr_value)?),
+                        b"alpha" => alpha = parse_component(&attr_value)?,
+                        _ => {}
+                    }
+                }
+
+                // item elements are also used for other purposes; only those
+                // with both value and color are colormap entries
+                if let (Some(value), Some(color)) = (value, color) {
+                    entries.push(ColormapEntry {
+                        value,
+                        color,
+                        alpha,
+                    });
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parse entries from a JSON array of objects with value and color hex
+/// properties, e.g., `[{"value": 1, "color": "#686868"}]`; colors may include
+/// alpha as "#RRGGBBAA"
+pub fn parse_json(contents: &str) -> Result<Vec<ColormapEntry>, Box<dyn Error>> {
+    let json: Value = serde_json::from_str(contents)?;
+    let items = json
+        .as_array()
+        .ok_or("colormap JSON must be an array of objects")?;
+
+    let mut entries: Vec<ColormapEntry> = Vec::with_capacity(items.len());
+
+    for item in items.iter() {
+        let value = match item.get("value") {
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::String(s)) => s.to_owned(),
+            _ => return Err(format!("colormap entry must have a value: {}", item))?,
+        };
+
+        let color = item
+            .get("color")
+            .and_then(|c| c.as_str())
+            .ok_or(format!("colormap entry must have a color: {}", item))?;
+
+        let (color, alpha) = Rgb8::from_hex_alpha(color)?;
+        entries.push(ColormapEntry {
+            value,
+            color,
+            alpha,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(value: &str, r: u8, g: u8, b: u8, alpha: u8) -> ColormapEntry {
+        ColormapEntry {
+            value: String::from(value),
+            color: Rgb8 { r, g, b },
+            alpha,
+        }
+    }
+
+    #[test]
+    fn test_parse_color_relief() {
+        let contents = r#"
+# comment
+1 104 104 104
+2,251,180,185,128
+3:197:27:138
+nv 0 0 0 0
+"#;
+        assert_eq!(
+            parse_color_relief(contents).unwrap(),
+            vec![
+                entry("1", 104, 104, 104, 255),
+                entry("2", 251, 180, 185, 128),
+                entry("3", 197, 27, 138, 255),
+            ]
+        );
+
+        assert!(parse_color_relief("50% 0 0 0").is_err());
+        assert!(parse_color_relief("1 0 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_clr() {
+        let contents = r#"# QGIS Generated Color Map Export File
+INTERPOLATION:EXACT
+1,104,104,104,255,Developed land
+2,251,180,185,128,2
+"#;
+        assert_eq!(
+            parse_clr(contents).unwrap(),
+            vec![
+                entry("1", 104, 104, 104, 255),
+                entry("2", 251, 180, 185, 128),
+            ]
+        );
+
+        assert_eq!(
+            parse_clr("1 104 104 104\n2 251 180 185\n").unwrap(),
+            vec![
+                entry("1", 104, 104, 104, 255),
+                entry("2", 251, 180, 185, 255),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_qml() {
+        let contents = r##"<!DOCTYPE qgis PUBLIC 'http://mrcc.com/qgis.dtd' 'SYSTEM'>
+<qgis version="3.28.0">
+  <pipe>
+    <rasterrenderer type="paletted" band="1" opacity="1">
+      <colorPalette>
+        <paletteEntry value="1" color="#686868" alpha="255" label="Developed &amp; urban"/>
+        <paletteEntry value="2" color="#fbb4b9" alpha="128" label="2"/>
+      </colorPalette>
+    </rasterrenderer>
+  </pipe>
+</qgis>
+"##;
+        assert_eq!(
+            parse_qml(contents).unwrap(),
+            vec![
+                entry("1", 104, 104, 104, 255),
+                entry("2", 251, 180, 185, 128),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let contents =
+            r##"[{"value": 1, "color": "#686868"}, {"value": "2", "color": "#fbb4b980"}]"##;
+        assert_eq!(
+            parse_json(contents).unwrap(),
+            vec![
+                entry("1", 104, 104, 104, 255),
+                entry("2", 251, 180, 185, 128),
+            ]
+        );
+
+        assert!(parse_json(r##"{"1": "#686868"}"##).is_err());
+        assert!(parse_json(r#"[{"value": 1}]"#).is_err());
+    }
+}
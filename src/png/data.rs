@@ -0,0 +1,84 @@
+use std::error::Error;
+
+/// Default zstd compression level for data tiles
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Values that can be written to data tiles as little-endian bytes
+pub trait DataValue: Copy {
+    fn from_f64(value: f64) -> Self;
+    fn extend_le_bytes(self, buffer: &mut Vec<u8>);
+}
+
+macro_rules! impl_data_value {
+    ($($t:ty),*) => {
+        $(
+            impl DataValue for $t {
+                fn from_f64(value: f64) -> Self {
+                    value as $t
+                }
+
+                fn extend_le_bytes(self, buffer: &mut Vec<u8>) {
+                    buffer.extend(self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_data_value!(u8, u16, i16, u32, f32, f64);
+
+/// Encoder for data tiles, which store the raw values of each tile as a
+/// zstd-compressed array of little-endian values in row-major order, so that
+/// clients can style and query values directly.
+#[derive(Debug)]
+pub struct DataEncoder {
+    level: i32,
+}
+
+impl DataEncoder {
+    pub fn new() -> DataEncoder {
+        DataEncoder {
+            level: COMPRESSION_LEVEL,
+        }
+    }
+
+    pub fn encode<T: DataValue>(&self, buffer: &[T]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(std::mem::size_of_val(buffer));
+        for &value in buffer.iter() {
+            value.extend_le_bytes(&mut bytes);
+        }
+
+        Ok(zstd::bulk::compress(&bytes, self.level)?)
+    }
+}
+
+impl Default for DataEncoder {
+    fn default() -> Self {
+        DataEncoder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8], size: usize) -> Vec<u8> {
+        zstd::bulk::decompress(data, size).unwrap()
+    }
+
+    #[test]
+    fn test_encode_u16() {
+        let encoder = DataEncoder::new();
+        let data = encoder.encode(&[1u16, 256u16, 65535u16]).unwrap();
+        assert_eq!(decode(&data, 6), [1, 0, 0, 1, 255, 255]);
+    }
+
+    #[test]
+    fn test_encode_f32() {
+        let encoder = DataEncoder::new();
+        let data = encoder.encode(&[1.5f32, f32::NAN]).unwrap();
+        let bytes = decode(&data, 8);
+        assert_eq!(f32::from_le_bytes(bytes[..4].try_into().unwrap()), 1.5);
+        assert!(f32::from_le_bytes(bytes[4..].try_into().unwrap()).is_nan());
+    }
+}
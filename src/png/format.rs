@@ -0,0 +1,158 @@
+use crate::png::color::ColormapRgb8;
+use crate::png::{
+    ColormapEncoder, Encode, GrayscaleEncoder, JpegEncoder, MixedEncoder, PixelValue, Pixels,
+    RGBAEncoder, RGBEncoder, WebPEncoder,
+};
+
+/// Default JPEG quality (1-100)
+pub const DEFAULT_JPEG_QUALITY: u8 = 75;
+
+/// Image format of rendered tiles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileFormat {
+    Png,
+    /// Lossless WebP
+    WebP,
+    /// JPEG; transparent pixels are encoded as black
+    Jpeg {
+        quality: u8,
+    },
+    /// JPEG for tiles where all pixels are fully opaque, otherwise PNG
+    Mixed {
+        quality: u8,
+    },
+    /// Raw values as zstd-compressed little-endian arrays; see DataEncoder
+    Data,
+}
+
+impl TileFormat {
+    /// Name of format as used in tileset metadata.  Mixed tilesets are
+    /// recorded as PNG, since that is the format of any tiles with
+    /// transparency; see formats() for the full list of formats.
+    pub fn name(&self) -> &str {
+        match self {
+            TileFormat::Png => "png",
+            TileFormat::WebP => "webp",
+            TileFormat::Jpeg { .. } => "jpg",
+            TileFormat::Mixed { .. } => "png",
+            TileFormat::Data => "zst",
+        }
+    }
+
+    /// Comma-delimited names of all formats that tiles may be encoded as
+    pub fn formats(&self) -> &str {
+        match self {
+            TileFormat::Mixed { .. } => "jpg,png",
+            _ => self.name(),
+        }
+    }
+
+    /// Set JPEG quality (1-100), if format includes JPEG
+    pub fn with_quality(self, quality: u8) -> TileFormat {
+        match self {
+            TileFormat::Jpeg { .. } => TileFormat::Jpeg { quality },
+            TileFormat::Mixed { .. } => TileFormat::Mixed { quality },
+            _ => self,
+        }
+    }
+}
+
+/// Create encoder for format that expands pixels to RGBA, or use png to create
+/// the encoder for PNG tiles
+fn encoder<T: PixelValue + 'static>(
+    format: TileFormat,
+    width: u32,
+    height: u32,
+    pixels: Pixels<T>,
+    opacity: u8,
+    png: impl FnOnce() -> Box<dyn Encode<T>>,
+) -> Box<dyn Encode<T>> {
+    match format {
+        TileFormat::Png => png(),
+        TileFormat::WebP => Box::new(WebPEncoder::new(width, height, pixels).with_opacity(opacity)),
+        TileFormat::Jpeg { quality } => Box::new(JpegEncoder::new(width, height, pixels, quality)),
+        TileFormat::Mixed { quality } => Box::new(
+            MixedEncoder::new(JpegEncoder::new(width, height, pixels, quality), png())
+                .with_opacity(opacity),
+        ),
+        TileFormat::Data => unreachable!("data tiles are not encoded as images"),
+    }
+}
+
+/// Create encoder for 8-bit grayscale buffers, where nodata is transparent;
+/// PNG tiles are packed at a reduced bit depth if max_value of all tiles is
+/// provided
+pub fn grayscale_encoder<T: PixelValue + 'static>(
+    format: TileFormat,
+    width: u32,
+    height: u32,
+    nodata: u8,
+    max_value: Option<u8>,
+    opacity: u8,
+) -> Box<dyn Encode<T>> {
+    encoder(
+        format,
+        width,
+        height,
+        Pixels::Grayscale { nodata },
+        opacity,
+        || {
+            let encoder = GrayscaleEncoder::new(width, height, nodata).with_opacity(opacity);
+            match max_value {
+                Some(max_value) => Box::new(encoder.with_max_value(max_value)),
+                None => Box::new(encoder),
+            }
+        },
+    )
+}
+
+/// Create encoder for 8-bit RGB buffers, where the color of nodata is
+/// transparent
+pub fn rgb_encoder<T: PixelValue + 'static>(
+    format: TileFormat,
+    width: u32,
+    height: u32,
+    nodata: u32,
+    opacity: u8,
+) -> Box<dyn Encode<T>> {
+    encoder(format, width, height, Pixels::rgb(nodata), opacity, || {
+        Box::new(RGBEncoder::new(width, height, nodata).with_opacity(opacity))
+    })
+}
+
+/// Create encoder for 8-bit RGBA buffers
+pub fn rgba_encoder<T: PixelValue + 'static>(
+    format: TileFormat,
+    width: u32,
+    height: u32,
+    opacity: u8,
+) -> Box<dyn Encode<T>> {
+    encoder(format, width, height, Pixels::Rgba, opacity, || {
+        Box::new(RGBAEncoder::new(width, height).with_opacity(opacity))
+    })
+}
+
+/// Create encoder for buffers of values that are converted to colors using
+/// colormap; opacity must already be applied to the colormap
+pub fn colormap_encoder<T: PixelValue + 'static>(
+    format: TileFormat,
+    width: u32,
+    height: u32,
+    colormap: ColormapRgb8<T>,
+) -> Box<dyn Encode<T>> {
+    let png_colormap = colormap.clone();
+    encoder(
+        format,
+        width,
+        height,
+        Pixels::Colormap(colormap),
+        255u8,
+        move || {
+            Box::new(ColormapEncoder {
+                width,
+                height,
+                colormap: png_colormap,
+            })
+        },
+    )
+}
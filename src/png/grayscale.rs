@@ -2,13 +2,15 @@ use png::{BitDepth, ColorType, Compression, Encoder, FilterType};
 use std::error::Error;
 use std::io::BufWriter;
 
-use crate::png::{Encode, PixelValue};
+use crate::png::{pack_8u_1bit, pack_8u_2bit, pack_8u_4bit, Encode, PixelValue};
 
 #[derive(Debug)]
 pub struct GrayscaleEncoder {
     width: u32,
     height: u32,
     nodata: u8,
+    opacity: u8,
+    depth: BitDepth,
 }
 
 impl GrayscaleEncoder {
@@ -17,8 +19,54 @@ impl GrayscaleEncoder {
             width,
             height,
             nodata,
+            opacity: 255u8,
+            depth: BitDepth::Eight,
         }
     }
+
+    /// Set maximum value of all tiles; tiles are written at the smallest bit
+    /// depth that holds this value and NODATA, so that the same value has the
+    /// same bit depth in every tile
+    pub fn with_max_value(mut self, max_value: u8) -> GrayscaleEncoder {
+        self.depth = match max_value.max(self.nodata) {
+            v if v < 2 => BitDepth::One,
+            v if v < 4 => BitDepth::Two,
+            v if v < 16 => BitDepth::Four,
+            _ => BitDepth::Eight,
+        };
+        self
+    }
+
+    /// Set opacity of all pixels except NODATA; if less than 255, tiles are
+    /// encoded as grayscale with alpha
+    pub fn with_opacity(mut self, opacity: u8) -> GrayscaleEncoder {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Pack values into bytes at bit depth; each row starts on a new byte
+    fn pack(&self, buffer: &[u8], depth: BitDepth) -> Vec<u8> {
+        let per_byte = match depth {
+            BitDepth::One => 8,
+            BitDepth::Two => 4,
+            _ => 2,
+        };
+
+        let mut pixels: Vec<u8> = Vec::with_capacity(buffer.len() / per_byte + 1);
+        for row in buffer.chunks(self.width as usize) {
+            for values in row.chunks(per_byte) {
+                // pad last byte of row with 0
+                let v = |i: usize| values.get(i).copied().unwrap_or(0u8);
+                pixels.push(match depth {
+                    BitDepth::One => pack_8u_1bit(v(0), v(1), v(2), v(3), v(4), v(5), v(6), v(7)),
+                    BitDepth::Two => pack_8u_2bit(v(0), v(1), v(2), v(3)),
+                    _ => pack_8u_4bit(v(0), v(1)),
+                });
+            }
+        }
+
+        pixels
+    }
 }
 
 impl<T: PixelValue> Encode<T> for GrayscaleEncoder {
@@ -32,19 +80,185 @@ impl<T: PixelValue> Encode<T> for GrayscaleEncoder {
 
         let mut encoder = Encoder::new(BufWriter::new(&mut png_buffer), self.width, self.height);
 
-        encoder.set_color(ColorType::Grayscale);
-        encoder.set_depth(BitDepth::Eight);
         // turn off filter, according to PNG book
         encoder.set_filter(FilterType::NoFilter);
         encoder.set_compression(Compression::Best);
 
-        // encode nodata as a 2 byte value per the spec, with value in high bits
-        encoder.set_trns(vec![0, self.nodata]);
+        if self.opacity == 255 {
+            let depth = self.depth;
+            encoder.set_color(ColorType::Grayscale);
+            encoder.set_depth(depth);
+            // encode nodata as a 2 byte value per the spec, with value in high bits
+            encoder.set_trns(vec![0, self.nodata]);
+
+            let mut writer = encoder.write_header()?;
+            match depth {
+                BitDepth::Eight => writer.write_image_data(buffer)?,
+                _ => writer.write_image_data(&self.pack(buffer, depth))?,
+            }
+            writer.finish()?;
+        } else {
+            // grayscale with alpha only supports 8 or 16 bit depths
+            encoder.set_color(ColorType::GrayscaleAlpha);
+            encoder.set_depth(BitDepth::Eight);
+
+            let mut pixels: Vec<u8> = Vec::with_capacity(buffer.len() * 2);
+            for &value in buffer.iter() {
+                if value == self.nodata {
+                    pixels.extend([0u8, 0u8]);
+                } else {
+                    pixels.extend([value, self.opacity]);
+                }
+            }
+
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&pixels)?;
+            writer.finish()?;
+        }
+
+        Ok(png_buffer)
+    }
+}
+
+/// Encoder for 16-bit grayscale PNGs that store uint16 values exactly, so that
+/// clients can decode values directly; NODATA is transparent
+#[derive(Debug)]
+pub struct Grayscale16Encoder {
+    width: u32,
+    height: u32,
+    nodata: u16,
+}
+
+impl Grayscale16Encoder {
+    pub fn new(width: u32, height: u32, nodata: u16) -> Grayscale16Encoder {
+        Grayscale16Encoder {
+            width,
+            height,
+            nodata,
+        }
+    }
+}
+
+impl Encode<u16> for Grayscale16Encoder {
+    fn encode(&self, buffer: &[u16]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut png_buffer: Vec<u8> = Vec::new();
+
+        let mut encoder = Encoder::new(BufWriter::new(&mut png_buffer), self.width, self.height);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(BitDepth::Sixteen);
+        encoder.set_filter(FilterType::NoFilter);
+        encoder.set_compression(Compression::Best);
+        // PNG stores 16-bit values, including tRNS, in big-endian order
+        encoder.set_trns(self.nodata.to_be_bytes().to_vec());
+
+        let mut pixels: Vec<u8> = Vec::with_capacity(buffer.len() * 2);
+        for &value in buffer.iter() {
+            pixels.extend(value.to_be_bytes());
+        }
 
         let mut writer = encoder.write_header()?;
-        writer.write_image_data(buffer)?;
+        writer.write_image_data(&pixels)?;
         writer.finish()?;
 
         Ok(png_buffer)
     }
+
+    fn encode_8bit(&self, _buffer: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        unimplemented!("encode_8bit() not implemented for Grayscale16Encoder, use encode() instead")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[0, 1, 1, 0, 0, 0, 1, 1, 1, 1], 0, BitDepth::One, vec![0b01100011, 0b11000000])]
+    #[case(&[0, 1, 2, 3, 3, 2, 1, 0, 1, 2], 0, BitDepth::Two, vec![0b00011011, 0b11100100, 0b01100000])]
+    #[case(&[0, 1, 2, 3, 3, 2, 1, 0, 1, 2], 15, BitDepth::Four, vec![0x01, 0x23, 0x32, 0x10, 0x12])]
+    #[case(&[0, 1, 2, 3, 3, 2, 1, 0, 1, 16], 0, BitDepth::Eight, vec![0, 1, 2, 3, 3, 2, 1, 0, 1, 16])]
+    fn test_encode_bit_depth(
+        #[case] buffer: &[u8],
+        #[case] nodata: u8,
+        #[case] expected_depth: BitDepth,
+        #[case] expected: Vec<u8>,
+    ) {
+        let max_value = buffer.iter().max().copied().unwrap();
+        let encoder = GrayscaleEncoder::new(10, 1, nodata).with_max_value(max_value);
+        let png_data = <GrayscaleEncoder as Encode<u8>>::encode_8bit(&encoder, buffer).unwrap();
+
+        let mut decoder = png::Decoder::new(&png_data[..]);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!(reader.info().bit_depth, expected_depth);
+        assert_eq!(
+            reader.info().trns.as_deref().and_then(|t| t.last()),
+            Some(&nodata)
+        );
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn test_encode_bit_depth_adjacent_tiles() {
+        // adjacent tiles with different maximum values use the same bit depth
+        // so that values are displayed consistently across tiles
+        let encoder = GrayscaleEncoder::new(2, 1, 0).with_max_value(3);
+        for (buffer, expected) in [([0u8, 1], [0b00010000u8]), ([2, 3], [0b10110000])] {
+            let png_data =
+                <GrayscaleEncoder as Encode<u8>>::encode_8bit(&encoder, &buffer).unwrap();
+
+            let mut decoder = png::Decoder::new(&png_data[..]);
+            decoder.set_transformations(png::Transformations::IDENTITY);
+            let mut reader = decoder.read_info().unwrap();
+            let mut pixels = vec![0u8; reader.output_buffer_size()];
+            reader.next_frame(&mut pixels).unwrap();
+
+            assert_eq!(reader.info().bit_depth, BitDepth::Two);
+            assert_eq!(pixels, expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_default_bit_depth() {
+        let encoder = GrayscaleEncoder::new(2, 1, 0);
+        let png_data = <GrayscaleEncoder as Encode<u8>>::encode_8bit(&encoder, &[0, 1]).unwrap();
+
+        let decoder = png::Decoder::new(&png_data[..]);
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().bit_depth, BitDepth::Eight);
+    }
+
+    #[test]
+    fn test_encode_opacity() {
+        let encoder = GrayscaleEncoder::new(2, 1, 0).with_opacity(128);
+        let png_data = <GrayscaleEncoder as Encode<u8>>::encode_8bit(&encoder, &[0, 200]).unwrap();
+
+        let decoder = png::Decoder::new(&png_data[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!(reader.info().color_type, ColorType::GrayscaleAlpha);
+        assert_eq!(pixels, [0, 0, 200, 128]);
+    }
+
+    #[test]
+    fn test_encode_16bit() {
+        let encoder = Grayscale16Encoder::new(3, 1, 65535);
+        let png_data = encoder.encode(&[0, 1000, 65535]).unwrap();
+
+        let mut decoder = png::Decoder::new(&png_data[..]);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!(reader.info().bit_depth, BitDepth::Sixteen);
+        assert_eq!(reader.info().trns.as_deref(), Some([255u8, 255].as_slice()));
+        assert_eq!(pixels, [0, 0, 3, 232, 255, 255]);
+    }
 }
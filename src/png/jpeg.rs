@@ -0,0 +1,130 @@
+use std::error::Error;
+
+use jpeg_encoder::ColorType;
+
+use crate::png::{is_opaque, Encode, PixelValue, Pixels};
+
+/// JPEG encoder.  JPEG does not support transparency, so all pixels are
+/// expanded to RGBA and alpha is discarded before encoding; transparent pixels
+/// are encoded as black.
+#[derive(Debug)]
+pub struct JpegEncoder<T: PixelValue> {
+    width: u16,
+    height: u16,
+    pixels: Pixels<T>,
+    quality: u8,
+}
+
+impl<T: PixelValue> JpegEncoder<T> {
+    pub fn new(width: u32, height: u32, pixels: Pixels<T>, quality: u8) -> JpegEncoder<T> {
+        JpegEncoder {
+            width: width as u16,
+            height: height as u16,
+            pixels,
+            quality,
+        }
+    }
+
+    fn encode_rgba(&self, rgba: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut jpeg_buffer: Vec<u8> = Vec::new();
+        jpeg_encoder::Encoder::new(&mut jpeg_buffer, self.quality).encode(
+            rgba,
+            self.width,
+            self.height,
+            ColorType::Rgba,
+        )?;
+
+        Ok(jpeg_buffer)
+    }
+}
+
+impl<T: PixelValue> Encode<T> for JpegEncoder<T> {
+    fn encode(&self, buffer: &[T]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.encode_rgba(&self.pixels.to_rgba(buffer, 255u8))
+    }
+
+    fn encode_8bit(&self, buffer: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.encode_rgba(&self.pixels.to_rgba_8bit(buffer, 255u8))
+    }
+}
+
+/// Encoder that writes JPEG for tiles where all pixels are fully opaque, and
+/// otherwise falls back to another encoder (e.g., PNG) that supports
+/// transparency, such as for tiles along the edges of the data.
+pub struct MixedEncoder<T: PixelValue> {
+    jpeg: JpegEncoder<T>,
+    fallback: Box<dyn Encode<T>>,
+    opacity: u8,
+}
+
+impl<T: PixelValue> MixedEncoder<T> {
+    pub fn new(jpeg: JpegEncoder<T>, fallback: Box<dyn Encode<T>>) -> MixedEncoder<T> {
+        MixedEncoder {
+            jpeg,
+            fallback,
+            opacity: 255u8,
+        }
+    }
+
+    /// Set opacity that is multiplied with the alpha of every pixel; any
+    /// opacity less than 255 means all tiles are encoded by the fallback encoder
+    pub fn with_opacity(mut self, opacity: u8) -> MixedEncoder<T> {
+        self.opacity = opacity;
+        self
+    }
+}
+
+impl<T: PixelValue> Encode<T> for MixedEncoder<T> {
+    fn encode(&self, buffer: &[T]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let rgba = self.jpeg.pixels.to_rgba(buffer, self.opacity);
+        if is_opaque(&rgba) {
+            return self.jpeg.encode_rgba(&rgba);
+        }
+        self.fallback.encode(buffer)
+    }
+
+    fn encode_8bit(&self, buffer: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let rgba = self.jpeg.pixels.to_rgba_8bit(buffer, self.opacity);
+        if is_opaque(&rgba) {
+            return self.jpeg.encode_rgba(&rgba);
+        }
+        self.fallback.encode_8bit(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::RGBEncoder;
+
+    const JPEG_SIGNATURE: [u8; 2] = [0xFF, 0xD8];
+    const PNG_SIGNATURE: [u8; 4] = [0x89, b'P', b'N', b'G'];
+
+    #[test]
+    fn test_encode_jpeg() {
+        let encoder = JpegEncoder::<u8>::new(2, 1, Pixels::Grayscale { nodata: 0 }, 90);
+        let jpeg_data = encoder.encode_8bit(&[0, 200]).unwrap();
+        assert_eq!(jpeg_data[..2], JPEG_SIGNATURE);
+    }
+
+    #[test]
+    fn test_encode_mixed() {
+        let encoder = MixedEncoder::<u8>::new(
+            JpegEncoder::new(2, 1, Pixels::rgb(0), 90),
+            Box::new(RGBEncoder::new(2, 1, 0)),
+        );
+
+        // fully opaque tile is encoded as JPEG
+        let data = encoder.encode_8bit(&[10, 10, 10, 255, 0, 0]).unwrap();
+        assert_eq!(data[..2], JPEG_SIGNATURE);
+
+        // tile with nodata is encoded as PNG
+        let data = encoder.encode_8bit(&[0, 0, 0, 255, 0, 0]).unwrap();
+        assert_eq!(data[..4], PNG_SIGNATURE);
+
+        // any opacity is encoded as PNG
+        let encoder = encoder.with_opacity(254);
+        let data = encoder.encode_8bit(&[10, 10, 10, 255, 0, 0]).unwrap();
+        assert_eq!(data[..4], PNG_SIGNATURE);
+    }
+}
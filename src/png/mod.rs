@@ -1,21 +1,45 @@
 use std::error::Error;
+use std::str::FromStr;
 
+pub use self::classes::*;
 pub use self::color::*;
 pub use self::colormap::*;
+pub use self::colormap_file::*;
+pub use self::data::*;
+pub use self::format::*;
 pub use self::grayscale::*;
+pub use self::jpeg::*;
+pub use self::pixels::*;
+pub use self::quantize::*;
+pub use self::ramp::*;
 pub use self::rgb::*;
+pub use self::rgba::*;
+pub use self::stretch::*;
 pub use self::util::*;
+pub use self::webp::*;
 
+mod classes;
 mod color;
 mod colormap;
+mod colormap_file;
+mod data;
+mod format;
 mod grayscale;
+mod jpeg;
+mod pixels;
+mod quantize;
+mod ramp;
 mod rgb;
+mod rgba;
+mod stretch;
 mod util;
+mod webp;
 
-pub trait PixelValue: Ord + Copy + From<u8> {}
+pub trait PixelValue: Ord + Copy + From<u8> + FromStr {}
 
 impl PixelValue for u8 {}
 impl PixelValue for u16 {}
+impl PixelValue for i16 {}
 impl PixelValue for u32 {}
 
 pub trait Encode<T: PixelValue> {
@@ -0,0 +1,151 @@
+use crate::png::color::{ColormapRgb8, Rgb8};
+use crate::png::{multiply_alpha, PixelValue};
+
+/// Layout of the buffers passed to encoders that do not support paletted
+/// images or a single transparent color; these match the PNG encoders, so that
+/// either can be used to encode the same buffers.  Buffers are expanded to
+/// RGBA before encoding.
+#[derive(Debug)]
+pub enum Pixels<T: PixelValue> {
+    /// 8-bit grayscale, where nodata is transparent
+    Grayscale { nodata: u8 },
+    /// 8-bit RGB, where the color of nodata is transparent
+    Rgb { nodata_color: Rgb8 },
+    /// 8-bit RGBA
+    Rgba,
+    /// Values that are converted to colors using colormap
+    Colormap(ColormapRgb8<T>),
+}
+
+impl<T: PixelValue> Pixels<T> {
+    pub fn rgb(nodata: u32) -> Pixels<T> {
+        Pixels::Rgb {
+            nodata_color: Rgb8::from_u32(nodata),
+        }
+    }
+
+    /// Expand buffer of values to RGBA using colormap, multiplying alpha by
+    /// opacity
+    pub fn to_rgba(&self, buffer: &[T], opacity: u8) -> Vec<u8> {
+        let colormap = match self {
+            Pixels::Colormap(colormap) => colormap,
+            _ => unimplemented!(
+                "to_rgba() only implemented for colormaps, use to_rgba_8bit() instead"
+            ),
+        };
+
+        let colors = colormap.get_colors();
+        let transparency = colormap.get_transparency();
+
+        let mut rgba: Vec<u8> = Vec::with_capacity(buffer.len() * 4);
+        for &value in buffer.iter() {
+            let index = colormap.get_index(value) as usize;
+            // entries after the last transparent entry are opaque
+            let alpha = transparency.get(index).copied().unwrap_or(255u8);
+            push_rgba(
+                &mut rgba,
+                colors[index * 3],
+                colors[index * 3 + 1],
+                colors[index * 3 + 2],
+                multiply_alpha(alpha, opacity),
+            );
+        }
+
+        rgba
+    }
+
+    /// Expand 8-bit buffer to RGBA, multiplying alpha by opacity
+    pub fn to_rgba_8bit(&self, buffer: &[u8], opacity: u8) -> Vec<u8> {
+        let mut rgba: Vec<u8> = Vec::with_capacity(buffer.len() * 4);
+
+        match self {
+            Pixels::Grayscale { nodata } => {
+                for &value in buffer.iter() {
+                    let alpha = if value == *nodata { 0u8 } else { opacity };
+                    push_rgba(&mut rgba, value, value, value, alpha);
+                }
+            }
+            Pixels::Rgb { nodata_color } => {
+                let nodata = [nodata_color.r, nodata_color.g, nodata_color.b];
+                for rgb in buffer.chunks_exact(3) {
+                    let alpha = if rgb == nodata { 0u8 } else { opacity };
+                    push_rgba(&mut rgba, rgb[0], rgb[1], rgb[2], alpha);
+                }
+            }
+            Pixels::Rgba => {
+                for pixel in buffer.chunks_exact(4) {
+                    let alpha = multiply_alpha(pixel[3], opacity);
+                    push_rgba(&mut rgba, pixel[0], pixel[1], pixel[2], alpha);
+                }
+            }
+            Pixels::Colormap(_) => {
+                unimplemented!(
+                    "to_rgba_8bit() not implemented for colormaps, use to_rgba() instead"
+                )
+            }
+        }
+
+        rgba
+    }
+}
+
+/// Append RGBA pixel, setting color of fully transparent pixels to 0 for
+/// better compression
+#[inline]
+fn push_rgba(rgba: &mut Vec<u8>, r: u8, g: u8, b: u8, alpha: u8) {
+    if alpha == 0 {
+        rgba.extend([0u8, 0u8, 0u8, 0u8]);
+    } else {
+        rgba.extend([r, g, b, alpha]);
+    }
+}
+
+/// Return true if all pixels of RGBA buffer are fully opaque
+pub fn is_opaque(rgba: &[u8]) -> bool {
+    rgba.chunks_exact(4).all(|pixel| pixel[3] == 255)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_rgba_8bit() {
+        let pixels = Pixels::<u8>::Grayscale { nodata: 0 };
+        assert_eq!(
+            pixels.to_rgba_8bit(&[0, 200], 128),
+            [0, 0, 0, 0, 200, 200, 200, 128]
+        );
+
+        let pixels = Pixels::<u8>::rgb(0);
+        assert_eq!(
+            pixels.to_rgba_8bit(&[0, 0, 0, 255, 0, 0], 255),
+            [0, 0, 0, 0, 255, 0, 0, 255]
+        );
+
+        let pixels = Pixels::<u8>::Rgba;
+        assert_eq!(
+            pixels.to_rgba_8bit(&[255, 0, 0, 0, 255, 0, 0, 255], 128),
+            [0, 0, 0, 0, 255, 0, 0, 128]
+        );
+    }
+
+    #[test]
+    fn test_to_rgba() {
+        let mut colormap = ColormapRgb8::<u16>::new(2, 0);
+        colormap.add_color(1000, Rgb8 { r: 255, g: 0, b: 0 });
+        colormap.add_color_alpha(2000, Rgb8 { r: 0, g: 0, b: 255 }, 128);
+
+        let pixels = Pixels::Colormap(colormap);
+        assert_eq!(
+            pixels.to_rgba(&[0, 1000, 2000, 3000], 255),
+            [0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 255, 128, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_is_opaque() {
+        assert!(is_opaque(&[255, 0, 0, 255, 0, 0, 0, 255]));
+        assert!(!is_opaque(&[255, 0, 0, 255, 0, 0, 0, 254]));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+
+use crate::png::color::Rgb8;
+
+/// Maximum number of colors in a quantized palette; index 0 is reserved for
+/// NODATA
+const MAX_COLORS: usize = 255;
+
+/// Maximum number of iterations used to refine k-means clusters
+const KMEANS_ITERATIONS: usize = 8;
+
+/// Maximum number of unique colors used to refine k-means clusters; larger
+/// sets of colors are evenly subsampled
+const KMEANS_MAX_COLORS: usize = 4096;
+
+/// Default maximum difference allowed in any channel between the original
+/// and quantized color of any pixel
+pub const DEFAULT_QUANTIZE_MAX_ERROR: u8 = 8;
+
+/// Method used to select the colors of a quantized palette
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantizeMethod {
+    /// Recursively split the box of colors with the widest range at the median
+    MedianCut,
+    /// Refine median cut colors using k-means clustering
+    KMeans,
+}
+
+/// Tile quantized to a palette of no more than 255 colors
+#[derive(Debug)]
+pub struct Quantized {
+    /// Index of each pixel: 0 for NODATA, otherwise 1 + index into palette
+    pub indexes: Vec<u8>,
+    pub palette: Vec<Rgb8>,
+}
+
+/// Lossy palette reduction of RGB tiles that have too many unique colors to be
+/// written as paletted PNGs
+#[derive(Debug, Clone, Copy)]
+pub struct Quantizer {
+    method: QuantizeMethod,
+    dither: bool,
+    max_error: u8,
+}
+
+impl Quantizer {
+    pub fn new(method: QuantizeMethod) -> Quantizer {
+        Quantizer {
+            method,
+            dither: false,
+            max_error: DEFAULT_QUANTIZE_MAX_ERROR,
+        }
+    }
+
+    /// Use Floyd-Steinberg dithering to diffuse quantization error to
+    /// neighboring pixels
+    pub fn with_dither(mut self, dither: bool) -> Quantizer {
+        self.dither = dither;
+        self
+    }
+
+    /// Set maximum difference allowed in any channel between the original and
+    /// quantized color of any pixel; use 0 to only allow exact colors
+    pub fn with_max_error(mut self, max_error: u8) -> Quantizer {
+        self.max_error = max_error;
+        self
+    }
+
+    /// Quantize 8-bit RGB buffer of width pixels per row; pixels where mask is
+    /// false are NODATA.  Returns None if the quantization error of any pixel
+    /// exceeds the maximum error.
+    pub fn quantize(&self, rgb: &[u8], mask: &[bool], width: usize) -> Option<Quantized> {
+        let mut histogram: HashMap<[u8; 3], u32> = HashMap::new();
+        for (pixel, _) in rgb.chunks_exact(3).zip(mask).filter(|&(_, &m)| m) {
+            *histogram.entry([pixel[0], pixel[1], pixel[2]]).or_insert(0) += 1;
+        }
+
+        // sort colors so that results do not depend on hash order
+        let mut colors: Vec<([u8; 3], u32)> = histogram.into_iter().collect();
+        colors.sort_unstable();
+
+        let mut palette = median_cut(&colors, MAX_COLORS);
+        if self.method == QuantizeMethod::KMeans {
+            palette = kmeans(
+                &subsample(&colors, KMEANS_MAX_COLORS),
+                &palette,
+                KMEANS_ITERATIONS,
+            );
+        }
+
+        let indexes = if self.dither {
+            dither_indexes(rgb, mask, width, &palette)
+        } else {
+            let mut cache: HashMap<[u8; 3], u8> = HashMap::new();
+            rgb.chunks_exact(3)
+                .zip(mask)
+                .map(|(pixel, &m)| match m {
+                    true => *cache
+                        .entry([pixel[0], pixel[1], pixel[2]])
+                        .or_insert_with(|| nearest(&palette, to_f32(pixel)) as u8 + 1),
+                    false => 0u8,
+                })
+                .collect()
+        };
+
+        for ((pixel, &m), &index) in rgb.chunks_exact(3).zip(mask).zip(indexes.iter()) {
+            if !m {
+                continue;
+            }
+            let color = palette[index as usize - 1];
+            let error = pixel[0]
+                .abs_diff(color.r)
+                .max(pixel[1].abs_diff(color.g))
+                .max(pixel[2].abs_diff(color.b));
+            if error > self.max_error {
+                return None;
+            }
+        }
+
+        Some(Quantized { indexes, palette })
+    }
+}
+
+fn to_f32(pixel: &[u8]) -> [f32; 3] {
+    [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32]
+}
+
+/// Return index of the palette color nearest to color
+fn nearest(palette: &[Rgb8], color: [f32; 3]) -> usize {
+    let mut min_index = 0;
+    let mut min_distance = f32::MAX;
+    for (i, c) in palette.iter().enumerate() {
+        let dr = c.r as f32 - color[0];
+        let dg = c.g as f32 - color[1];
+        let db = c.b as f32 - color[2];
+        let distance = dr * dr + dg * dg + db * db;
+        if distance < min_distance {
+            min_index = i;
+            min_distance = distance;
+        }
+    }
+    min_index
+}
+
+/// Return mean color of (color, count) pairs, weighted by count
+fn mean_color(colors: &[([u8; 3], u32)]) -> Rgb8 {
+    let mut sums = [0u64; 3];
+    let mut total = 0u64;
+    for &(color, count) in colors {
+        for c in 0..3 {
+            sums[c] += color[c] as u64 * count as u64;
+        }
+        total += count as u64;
+    }
+
+    let mean = |sum: u64| ((sum + total / 2) / total) as u8;
+    Rgb8 {
+        r: mean(sums[0]),
+        g: mean(sums[1]),
+        b: mean(sums[2]),
+    }
+}
+
+/// Return (channel, range) of the channel with the widest range of values
+fn widest_channel(colors: &[([u8; 3], u32)]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = colors.iter().map(|(color, _)| color[c]).min().unwrap();
+            let max = colors.iter().map(|(color, _)| color[c]).max().unwrap();
+            (c, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap()
+}
+
+/// Box of unique (color, count) pairs with its widest channel and range
+struct ColorBox {
+    colors: Vec<([u8; 3], u32)>,
+    channel: usize,
+    range: u8,
+}
+
+impl ColorBox {
+    fn new(colors: Vec<([u8; 3], u32)>) -> ColorBox {
+        let (channel, range) = widest_channel(&colors);
+        ColorBox {
+            colors,
+            channel,
+            range,
+        }
+    }
+}
+
+/// Select up to max_colors colors from unique (color, count) pairs using
+/// median cut
+fn median_cut(colors: &[([u8; 3], u32)], max_colors: usize) -> Vec<Rgb8> {
+    if colors.is_empty() {
+        return Vec::new();
+    }
+
+    let mut boxes: Vec<ColorBox> = vec![ColorBox::new(colors.to_vec())];
+
+    while boxes.len() < max_colors {
+        // split the box with the widest range of values in any channel; boxes
+        // with a single color have a range of 0
+        let Some(i) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.range > 0)
+            .max_by_key(|(_, b)| b.range)
+            .map(|(i, _)| i)
+        else {
+            break;
+        };
+
+        let ColorBox {
+            colors: mut left,
+            channel,
+            ..
+        } = boxes.swap_remove(i);
+        left.sort_unstable_by_key(|&(color, _)| color[channel]);
+
+        // split at the median, weighted by count
+        let total: u64 = left.iter().map(|&(_, count)| count as u64).sum();
+        let mut acc = 0u64;
+        let mut split = left.len() - 1;
+        for (j, &(_, count)) in left.iter().enumerate() {
+            acc += count as u64;
+            if acc * 2 >= total {
+                split = j + 1;
+                break;
+            }
+        }
+        let right = left.split_off(split.clamp(1, left.len() - 1));
+
+        boxes.push(ColorBox::new(left));
+        boxes.push(ColorBox::new(right));
+    }
+
+    boxes.iter().map(|b| mean_color(&b.colors)).collect()
+}
+
+/// Return evenly spaced subset of no more than max_colors (color, count)
+/// pairs
+fn subsample(colors: &[([u8; 3], u32)], max_colors: usize) -> Vec<([u8; 3], u32)> {
+    if colors.len() <= max_colors {
+        return colors.to_vec();
+    }
+    (0..max_colors)
+        .map(|i| colors[i * colors.len() / max_colors])
+        .collect()
+}
+
+/// Refine palette using k-means clustering of unique (color, count) pairs
+fn kmeans(colors: &[([u8; 3], u32)], palette: &[Rgb8], iterations: usize) -> Vec<Rgb8> {
+    let mut palette = palette.to_vec();
+
+    for _ in 0..iterations {
+        let mut sums = vec![[0u64; 3]; palette.len()];
+        let mut totals = vec![0u64; palette.len()];
+
+        for &(color, count) in colors {
+            let i = nearest(&palette, to_f32(&color));
+            for c in 0..3 {
+                sums[i][c] += color[c] as u64 * count as u64;
+            }
+            totals[i] += count as u64;
+        }
+
+        let mut changed = false;
+        for (i, color) in palette.iter_mut().enumerate() {
+            if totals[i] == 0 {
+                continue;
+            }
+            let mean = |sum: u64| ((sum + totals[i] / 2) / totals[i]) as u8;
+            let updated = Rgb8 {
+                r: mean(sums[i][0]),
+                g: mean(sums[i][1]),
+                b: mean(sums[i][2]),
+            };
+            if updated != *color {
+                *color = updated;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    palette
+}
+
+/// Map pixels to palette using Floyd-Steinberg dithering; error is only
+/// diffused to pixels that are not NODATA
+fn dither_indexes(rgb: &[u8], mask: &[bool], width: usize, palette: &[Rgb8]) -> Vec<u8> {
+    let size = mask.len();
+    let mut indexes = vec![0u8; size];
+    let mut errors = vec![[0f32; 3]; size];
+
+    for i in 0..size {
+        if !mask[i] {
+            continue;
+        }
+
+        let pixel = to_f32(&rgb[i * 3..i * 3 + 3]);
+        let target = [
+            (pixel[0] + errors[i][0]).clamp(0., 255.),
+            (pixel[1] + errors[i][1]).clamp(0., 255.),
+            (pixel[2] + errors[i][2]).clamp(0., 255.),
+        ];
+        let index = nearest(palette, target);
+        indexes[i] = index as u8 + 1;
+
+        let color = palette[index];
+        let error = [
+            target[0] - color.r as f32,
+            target[1] - color.g as f32,
+            target[2] - color.b as f32,
+        ];
+
+        let x = i % width;
+        let mut diffuse = |j: usize, weight: f32| {
+            if j < size && mask[j] {
+                for c in 0..3 {
+                    errors[j][c] += error[c] * weight;
+                }
+            }
+        };
+        if x + 1 < width {
+            diffuse(i + 1, 7. / 16.);
+            diffuse(i + width + 1, 1. / 16.);
+        }
+        if x > 0 {
+            diffuse(i + width - 1, 3. / 16.);
+        }
+        diffuse(i + width, 5. / 16.);
+    }
+
+    indexes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Create RGB gradient with 1024 unique colors
+    fn gradient() -> (Vec<u8>, Vec<bool>) {
+        let mut rgb: Vec<u8> = Vec::new();
+        for i in 0..1024u32 {
+            rgb.extend([(i % 256) as u8, (i / 4) as u8, 128u8]);
+        }
+        (rgb, vec![true; 1024])
+    }
+
+    #[test]
+    fn median_cut_exact() {
+        let colors = [([0u8, 0, 0], 10), ([255u8, 0, 0], 5), ([0u8, 0, 255], 1)];
+        let mut palette = median_cut(&colors, 255);
+        palette.sort_unstable_by_key(|c| (c.r, c.g, c.b));
+        assert_eq!(
+            palette,
+            [
+                Rgb8 { r: 0, g: 0, b: 0 },
+                Rgb8 { r: 0, g: 0, b: 255 },
+                Rgb8 { r: 255, g: 0, b: 0 }
+            ]
+        );
+    }
+
+    #[test]
+    fn median_cut_max_colors() {
+        let colors: Vec<([u8; 3], u32)> = (0..=255u8).map(|i| ([i, i, i], 1)).collect();
+        assert_eq!(median_cut(&colors, 16).len(), 16);
+    }
+
+    #[test]
+    fn subsample_max_colors() {
+        let colors: Vec<([u8; 3], u32)> = (0..=255u8).map(|i| ([i, i, i], 1)).collect();
+        assert_eq!(subsample(&colors, 1024), colors);
+
+        let sampled = subsample(&colors, 16);
+        assert_eq!(sampled.len(), 16);
+        assert_eq!(sampled[0], ([0, 0, 0], 1));
+        assert_eq!(sampled[15], ([240, 240, 240], 1));
+    }
+
+    #[rstest]
+    #[case(QuantizeMethod::MedianCut)]
+    #[case(QuantizeMethod::KMeans)]
+    fn quantize_unique_colors(#[case] method: QuantizeMethod) {
+        // 256x256 tile where every pixel has a unique color
+        let rgb: Vec<u8> = (0..256 * 256u32)
+            .flat_map(|i| [(i % 256) as u8, (i / 256) as u8, 128])
+            .collect();
+        let mask = vec![true; 256 * 256];
+
+        let quantized = Quantizer::new(method)
+            .with_max_error(255)
+            .quantize(&rgb, &mask, 256)
+            .unwrap();
+        assert_eq!(quantized.palette.len(), 255);
+    }
+
+    #[rstest]
+    #[case(QuantizeMethod::MedianCut, false)]
+    #[case(QuantizeMethod::MedianCut, true)]
+    #[case(QuantizeMethod::KMeans, false)]
+    #[case(QuantizeMethod::KMeans, true)]
+    fn quantize(#[case] method: QuantizeMethod, #[case] dither: bool) {
+        let (rgb, mut mask) = gradient();
+        mask[0] = false;
+
+        let quantized = Quantizer::new(method)
+            .with_dither(dither)
+            .with_max_error(16)
+            .quantize(&rgb, &mask, 32)
+            .unwrap();
+        assert!(quantized.palette.len() <= 255);
+        assert_eq!(quantized.indexes[0], 0);
+        assert!(quantized.indexes[1..].iter().all(|&i| i > 0));
+    }
+
+    #[test]
+    fn quantize_max_error() {
+        let (rgb, mask) = gradient();
+        let quantizer = Quantizer::new(QuantizeMethod::MedianCut).with_max_error(0);
+        assert!(quantizer.quantize(&rgb, &mask, 32).is_none());
+
+        // exact colors are never rejected
+        let quantized = quantizer.quantize(&rgb[..300], &mask[..100], 10).unwrap();
+        assert_eq!(quantized.palette.len(), 100);
+    }
+}
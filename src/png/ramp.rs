@@ -0,0 +1,249 @@
+use std::error::Error;
+
+use crate::png::color::{named_palette, ColormapRgb8, Rgb8};
+
+/// Color space used to interpolate colors between stops
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Rgb,
+    /// CIE L*a*b*, which gives perceptually smoother gradients
+    Lab,
+}
+
+/// Continuous color ramp with colors linearly interpolated between stops.
+/// Values outside the range of the stops are clamped to the colors of the
+/// first or last stop.
+#[derive(Debug)]
+pub struct ColorRamp {
+    stops: Vec<(f64, Rgb8)>,
+    interpolation: Interpolation,
+}
+
+impl ColorRamp {
+    pub fn new(mut stops: Vec<(f64, Rgb8)>) -> Result<ColorRamp, Box<dyn Error>> {
+        if stops.len() < 2 {
+            return Err("color ramp must have at least 2 stops")?;
+        }
+
+        if stops.iter().any(|(value, _)| !value.is_finite()) {
+            return Err("color ramp stops must be finite values")?;
+        }
+
+        stops.sort_by(|l, r| l.0.total_cmp(&r.0));
+
+        Ok(ColorRamp {
+            stops,
+            interpolation: Interpolation::Rgb,
+        })
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> ColorRamp {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Create a color ramp from colors spaced at equal intervals between
+    /// min and max
+    pub fn from_colors(colors: &[Rgb8], min: f64, max: f64) -> Result<ColorRamp, Box<dyn Error>> {
+        if min >= max {
+            return Err("color ramp minimum must be less than maximum")?;
+        }
+
+        let step = (max - min) / (colors.len() - 1) as f64;
+        ColorRamp::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (min + i as f64 * step, c))
+                .collect(),
+        )
+    }
+
+    /// Return true if ramp_str is a named palette with range, e.g.,
+    /// "viridis:0:100"
+    pub fn is_named(ramp_str: &str) -> bool {
+        let parts: Vec<&str> = ramp_str.split(':').collect();
+        parts.len() == 3 && named_palette(parts[0].trim()).is_some()
+    }
+
+    /// Parse a color ramp from comma-delimited value:hex color stops, e.g.,
+    /// "<value>:<hex>,<value>:<hex>", or from a named palette with range as
+    /// "<name>:<min>:<max>", e.g., "viridis:0:100"
+    pub fn parse(ramp_str: &str) -> Result<ColorRamp, Box<dyn Error>> {
+        if ColorRamp::is_named(ramp_str) {
+            let parts: Vec<&str> = ramp_str.split(':').map(|p| p.trim()).collect();
+            let colors = named_palette(parts[0]).unwrap();
+            return ColorRamp::from_colors(&colors, parts[1].parse()?, parts[2].parse()?);
+        }
+
+        let mut stops: Vec<(f64, Rgb8)> = Vec::new();
+
+        for entry in ramp_str.split(',') {
+            let (value, color) = entry
+                .split_once(':')
+                .ok_or(format!("invalid color ramp stop: {}", entry))?;
+            stops.push((value.trim().parse()?, Rgb8::from_hex(color.trim())?));
+        }
+
+        ColorRamp::new(stops)
+    }
+
+    pub fn min(&self) -> f64 {
+        self.stops[0].0
+    }
+
+    pub fn max(&self) -> f64 {
+        self.stops[self.stops.len() - 1].0
+    }
+
+    /// Return interpolated color for value
+    pub fn color(&self, value: f64) -> Rgb8 {
+        // number of stops with values less than or equal to value
+        let index = self.stops.partition_point(|(v, _)| *v <= value);
+
+        if index == 0 {
+            return self.stops[0].1;
+        }
+        if index == self.stops.len() {
+            return self.stops[index - 1].1;
+        }
+
+        let (left_value, left) = self.stops[index - 1];
+        let (right_value, right) = self.stops[index];
+        let t = (value - left_value) / (right_value - left_value);
+
+        match self.interpolation {
+            Interpolation::Rgb => Rgb8 {
+                r: lerp(left.r, right.r, t),
+                g: lerp(left.g, right.g, t),
+                b: lerp(left.b, right.b, t),
+            },
+            Interpolation::Lab => {
+                let (l, r) = (left.to_lab(), right.to_lab());
+                Rgb8::from_lab([
+                    l[0] + (r[0] - l[0]) * t,
+                    l[1] + (r[1] - l[1]) * t,
+                    l[2] + (r[2] - l[2]) * t,
+                ])
+            }
+        }
+    }
+
+    /// Create a 256 color palette from the color ramp, where index 0 is
+    /// reserved for NODATA and indexes 1-255 sample the ramp at equal intervals
+    /// between min and max.  Values must be stretched to 1-255 using a
+    /// LinearStretch between min and max before encoding with this palette.
+    pub fn palette(&self) -> ColormapRgb8<u8> {
+        let mut colormap = ColormapRgb8::<u8>::new(255, 0u8);
+        let step = (self.max() - self.min()) / 254.;
+
+        for i in 1u8..=255u8 {
+            colormap.add_color(i, self.color(self.min() + (i - 1) as f64 * step));
+        }
+
+        colormap
+    }
+}
+
+#[inline]
+fn lerp(left: u8, right: u8, t: f64) -> u8 {
+    (left as f64 + (right as f64 - left as f64) * t).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse() {
+        let ramp = ColorRamp::parse("10:#FFFFFF,-10:#000000").unwrap();
+        assert_eq!(ramp.min(), -10.);
+        assert_eq!(ramp.max(), 10.);
+    }
+
+    #[test]
+    fn test_parse_named() {
+        let ramp = ColorRamp::parse("viridis:-10:10").unwrap();
+        assert_eq!(ramp.min(), -10.);
+        assert_eq!(ramp.max(), 10.);
+        assert_eq!(ramp.stops.len(), 10);
+        assert_eq!(
+            ramp.color(10.),
+            Rgb8 {
+                r: 0xfd,
+                g: 0xe7,
+                b: 0x25
+            }
+        );
+
+        assert!(ColorRamp::is_named("Magma:0:1"));
+        assert!(!ColorRamp::is_named("0:#000000,1:#FFFFFF"));
+        assert!(!ColorRamp::is_named("unknown:0:1"));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("viridis:10:0")]
+    #[case("viridis:a:1")]
+    #[case("0:#000000")]
+    #[case("0:#000000,1")]
+    #[case("0:#000000,a:#FFFFFF")]
+    #[case("0:#000000,1:#FFF")]
+    fn test_parse_invalid(#[case] ramp_str: &str) {
+        assert!(ColorRamp::parse(ramp_str).is_err());
+    }
+
+    #[rstest]
+    #[case(-1., Rgb8{r: 0, g: 0, b: 0})]
+    #[case(0., Rgb8{r: 0, g: 0, b: 0})]
+    #[case(5., Rgb8{r: 128, g: 0, b: 0})]
+    #[case(10., Rgb8{r: 255, g: 0, b: 0})]
+    #[case(15., Rgb8{r: 255, g: 0, b: 128})]
+    #[case(20., Rgb8{r: 255, g: 0, b: 255})]
+    #[case(100., Rgb8{r: 255, g: 0, b: 255})]
+    fn test_color(#[case] value: f64, #[case] expected: Rgb8) {
+        let ramp = ColorRamp::parse("0:#000000,10:#FF0000,20:#FF00FF").unwrap();
+        assert_eq!(ramp.color(value), expected);
+    }
+
+    #[test]
+    fn test_color_lab() {
+        let ramp = ColorRamp::parse("0:#000000,1:#FFFFFF")
+            .unwrap()
+            .with_interpolation(Interpolation::Lab);
+        assert_eq!(ramp.color(0.), Rgb8 { r: 0, g: 0, b: 0 });
+        assert_eq!(
+            ramp.color(1.),
+            Rgb8 {
+                r: 255,
+                g: 255,
+                b: 255
+            }
+        );
+        // midpoint in Lab is perceptually mid-gray, which is darker than the
+        // midpoint in RGB (128)
+        assert_eq!(
+            ramp.color(0.5),
+            Rgb8 {
+                r: 119,
+                g: 119,
+                b: 119
+            }
+        );
+    }
+
+    #[test]
+    fn test_palette() {
+        let ramp = ColorRamp::parse("0:#000000,254:#FE0000").unwrap();
+        let palette = ramp.palette();
+        assert_eq!(palette.len(), 256);
+        assert_eq!(palette.get_index(0), 0);
+        assert_eq!(palette.get_index(255), 255);
+
+        let colors = palette.get_colors();
+        assert_eq!(colors[3..6], [0, 0, 0]);
+        assert_eq!(colors[6..9], [1, 0, 0]);
+        assert_eq!(colors[765..768], [254, 0, 0]);
+    }
+}
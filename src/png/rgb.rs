@@ -10,6 +10,7 @@ pub struct RGBEncoder {
     width: u32,
     height: u32,
     nodata_color: Rgb8,
+    opacity: u8,
 }
 
 impl RGBEncoder {
@@ -18,8 +19,16 @@ impl RGBEncoder {
             width,
             height,
             nodata_color: Rgb8::from_u32(nodata),
+            opacity: 255u8,
         }
     }
+
+    /// Set opacity of all pixels except NODATA; if less than 255, tiles are
+    /// encoded as RGBA
+    pub fn with_opacity(mut self, opacity: u8) -> RGBEncoder {
+        self.opacity = opacity;
+        self
+    }
 }
 
 impl<T: PixelValue> Encode<T> for RGBEncoder {
@@ -32,26 +41,149 @@ impl<T: PixelValue> Encode<T> for RGBEncoder {
 
         let mut encoder = Encoder::new(BufWriter::new(&mut png_buffer), self.width, self.height);
 
-        encoder.set_color(ColorType::Rgb);
         encoder.set_depth(BitDepth::Eight);
         encoder.set_compression(Compression::Best);
         // disabling filter appears to give smaller files for u32 data
         encoder.set_filter(FilterType::NoFilter);
 
-        // encode nodata as a 2 byte RGB values per the spec, with value in high bits
-        encoder.set_trns(vec![
-            0,
-            self.nodata_color.r,
-            0,
-            self.nodata_color.g,
-            0,
-            self.nodata_color.b,
-        ]);
+        if self.opacity == 255 {
+            encoder.set_color(ColorType::Rgb);
+            // encode nodata as a 2 byte RGB values per the spec, with value in high bits
+            encoder.set_trns(vec![
+                0,
+                self.nodata_color.r,
+                0,
+                self.nodata_color.g,
+                0,
+                self.nodata_color.b,
+            ]);
+
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(buffer)?;
+            writer.finish()?;
+        } else {
+            encoder.set_color(ColorType::Rgba);
+
+            let nodata = [
+                self.nodata_color.r,
+                self.nodata_color.g,
+                self.nodata_color.b,
+            ];
+            let mut pixels: Vec<u8> = Vec::with_capacity(buffer.len() / 3 * 4);
+            for rgb in buffer.chunks_exact(3) {
+                pixels.extend(rgb);
+                pixels.push(if rgb == nodata { 0u8 } else { self.opacity });
+            }
 
-        let mut writer = encoder.write_header()?;
-        writer.write_image_data(buffer)?;
-        writer.finish()?;
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&pixels)?;
+            writer.finish()?;
+        }
 
         Ok(png_buffer)
     }
 }
+
+/// RGB encoding of elevation values for terrain rendering
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElevationFormat {
+    /// Mapbox Terrain-RGB
+    TerrainRGB,
+    /// Mapzen / Tilezen Terrarium
+    Terrarium,
+}
+
+impl ElevationFormat {
+    /// Name of encoding as used by map clients
+    pub fn name(&self) -> &str {
+        match self {
+            ElevationFormat::TerrainRGB => "mapbox",
+            ElevationFormat::Terrarium => "terrarium",
+        }
+    }
+}
+
+/// Encodes elevation values to 24-bit RGB colors, where
+/// elevation = base + ((R * 256 * 256) + (G * 256) + B) * interval
+///
+/// Both formats use this equation; Terrain-RGB defaults to a base of -10000
+/// and interval of 0.1, and Terrarium uses a base of -32768 and interval of
+/// 1/256.  The lowest encoded value (0,0,0) is reserved for NODATA.
+#[derive(Debug, Clone, Copy)]
+pub struct ElevationEncoding {
+    pub format: ElevationFormat,
+    base: f64,
+    interval: f64,
+}
+
+impl ElevationEncoding {
+    pub fn new(format: ElevationFormat, base: Option<f64>, interval: Option<f64>) -> Self {
+        let (default_base, default_interval) = match format {
+            ElevationFormat::TerrainRGB => (-10000., 0.1),
+            ElevationFormat::Terrarium => (-32768., 1. / 256.),
+        };
+
+        ElevationEncoding {
+            format,
+            base: base.unwrap_or(default_base),
+            interval: interval.unwrap_or(default_interval),
+        }
+    }
+
+    #[inline]
+    pub fn encode(&self, value: f64) -> Rgb8 {
+        Rgb8::from_u32(
+            ((value - self.base) / self.interval)
+                .round()
+                .clamp(1., 16777215.) as u32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_encode_opacity() {
+        let encoder = RGBEncoder::new(2, 1, 0).with_opacity(128);
+        let png_data =
+            <RGBEncoder as Encode<u8>>::encode_8bit(&encoder, &[0, 0, 0, 255, 0, 0]).unwrap();
+
+        let decoder = png::Decoder::new(&png_data[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!(reader.info().color_type, ColorType::Rgba);
+        assert_eq!(pixels, [0, 0, 0, 0, 255, 0, 0, 128]);
+    }
+
+    #[rstest]
+    #[case(ElevationFormat::TerrainRGB, 0., Rgb8{r: 1, g: 134, b: 160})]
+    #[case(ElevationFormat::TerrainRGB, 8848.86, Rgb8{r: 2, g: 224, b: 73})]
+    #[case(ElevationFormat::TerrainRGB, -10000., Rgb8{r: 0, g: 0, b: 1})]
+    #[case(ElevationFormat::Terrarium, 0., Rgb8{r: 128, g: 0, b: 0})]
+    #[case(ElevationFormat::Terrarium, 100.5, Rgb8{r: 128, g: 100, b: 128})]
+    #[case(ElevationFormat::Terrarium, -100.5, Rgb8{r: 127, g: 155, b: 128})]
+    fn test_encode_elevation(
+        #[case] format: ElevationFormat,
+        #[case] value: f64,
+        #[case] expected: Rgb8,
+    ) {
+        assert_eq!(
+            ElevationEncoding::new(format, None, None).encode(value),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_encode_elevation_custom() {
+        let encoding = ElevationEncoding::new(ElevationFormat::TerrainRGB, Some(0.), Some(1.));
+        assert_eq!(
+            encoding.encode(65536. + 256. + 1.),
+            Rgb8 { r: 1, g: 1, b: 1 }
+        );
+    }
+}
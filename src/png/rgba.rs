@@ -0,0 +1,81 @@
+use png::{AdaptiveFilterType, BitDepth, ColorType, Compression, Encoder};
+use std::error::Error;
+use std::io::BufWriter;
+
+use crate::png::{multiply_alpha, Encode, PixelValue};
+
+#[derive(Debug)]
+pub struct RGBAEncoder {
+    width: u32,
+    height: u32,
+    opacity: u8,
+}
+
+impl RGBAEncoder {
+    pub fn new(width: u32, height: u32) -> RGBAEncoder {
+        RGBAEncoder {
+            width,
+            height,
+            opacity: 255u8,
+        }
+    }
+
+    /// Set opacity that is multiplied with the alpha of every pixel
+    pub fn with_opacity(mut self, opacity: u8) -> RGBAEncoder {
+        self.opacity = opacity;
+        self
+    }
+}
+
+impl<T: PixelValue> Encode<T> for RGBAEncoder {
+    fn encode(&self, _buffer: &[T]) -> Result<Vec<u8>, Box<dyn Error>> {
+        unimplemented!("encode() not implemented for RGBAEncoder, use encode_8bit() instead")
+    }
+
+    fn encode_8bit(&self, buffer: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut png_buffer: Vec<u8> = Vec::new();
+
+        let mut encoder = Encoder::new(BufWriter::new(&mut png_buffer), self.width, self.height);
+
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        encoder.set_compression(Compression::Best);
+        // unlike other encoders, filtering gives smaller files for imagery
+        encoder.set_adaptive_filter(AdaptiveFilterType::Adaptive);
+
+        let mut writer = encoder.write_header()?;
+        if self.opacity == 255 {
+            writer.write_image_data(buffer)?;
+        } else {
+            let mut pixels = buffer.to_vec();
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel[3] = multiply_alpha(pixel[3], self.opacity);
+            }
+            writer.write_image_data(&pixels)?;
+        }
+        writer.finish()?;
+
+        Ok(png_buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let encoder = RGBAEncoder::new(2, 1);
+        let png_data =
+            <RGBAEncoder as Encode<u8>>::encode_8bit(&encoder, &[255, 0, 0, 255, 0, 0, 0, 0])
+                .unwrap();
+
+        let decoder = png::Decoder::new(&png_data[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!(reader.info().color_type, ColorType::Rgba);
+        assert_eq!(pixels, [255, 0, 0, 255, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::array::is_nodata;
+
+/// Linear stretch of values between min and max to 8-bit values.
+///
+/// Value 0 is reserved for NODATA, so values are stretched to 1-255; values
+/// outside min and max are clamped to that range.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearStretch {
+    min: f64,
+    scale: f64,
+}
+
+impl LinearStretch {
+    pub fn new(min: f64, max: f64) -> LinearStretch {
+        let range = max - min;
+
+        LinearStretch {
+            min,
+            // if all values are equal, stretch them to the lowest value
+            scale: if range > 0. { 254. / range } else { 0. },
+        }
+    }
+
+    #[inline]
+    pub fn apply(&self, value: f64) -> u8 {
+        ((value - self.min) * self.scale).round().clamp(0., 254.) as u8 + 1
+    }
+
+    /// Stretch all values in buffer into out; NODATA and NaN values are set to 0
+    pub fn stretch<T: Copy + PartialEq + Into<f64>>(
+        &self,
+        buffer: &[T],
+        nodata: T,
+        out: &mut [u8],
+    ) {
+        for (i, &value) in buffer.iter().enumerate() {
+            out[i] = if is_nodata(value, nodata) {
+                0u8
+            } else {
+                self.apply(value.into())
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0., 100., 0., 1u8)]
+    #[case(0., 100., 100., 255u8)]
+    #[case(0., 100., 50., 128u8)]
+    #[case(0., 100., -10., 1u8)]
+    #[case(0., 100., 1000., 255u8)]
+    #[case(-1000., 1000., 0., 128u8)]
+    #[case(10., 10., 10., 1u8)]
+    fn test_apply(#[case] min: f64, #[case] max: f64, #[case] value: f64, #[case] expected: u8) {
+        assert_eq!(LinearStretch::new(min, max).apply(value), expected);
+    }
+
+    #[test]
+    fn test_stretch() {
+        let stretch = LinearStretch::new(0., 1000.);
+        let buffer = [0u16, 65535u16, 500u16, 1000u16];
+        let mut out = [0u8; 4];
+        stretch.stretch(&buffer, 65535u16, &mut out);
+        assert_eq!(out, [1u8, 0u8, 128u8, 255u8]);
+    }
+
+    #[test]
+    fn test_stretch_float() {
+        let stretch = LinearStretch::new(0., 1.);
+        let buffer = [0f32, f32::NAN, 0.5f32, -9999f32];
+        let mut out = [0u8; 4];
+        stretch.stretch(&buffer, -9999f32, &mut out);
+        assert_eq!(out, [1u8, 0u8, 128u8, 0u8]);
+    }
+}
@@ -15,3 +15,9 @@ pub fn pack_8u_2bit(v1: u8, v2: u8, v3: u8, v4: u8) -> u8 {
 pub fn pack_8u_4bit(v1: u8, v2: u8) -> u8 {
     v1 << 4u8 | v2
 }
+
+/// Multiply two 8-bit alpha values, e.g., to apply opacity to an alpha value
+#[inline]
+pub fn multiply_alpha(alpha: u8, opacity: u8) -> u8 {
+    ((alpha as u16 * opacity as u16 + 127) / 255) as u8
+}
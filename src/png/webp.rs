@@ -0,0 +1,100 @@
+use std::error::Error;
+
+use image_webp::ColorType;
+
+use crate::png::{Encode, PixelValue, Pixels};
+
+/// Lossless WebP encoder.  WebP does not support paletted images or a single
+/// transparent color, so all pixels are expanded to RGBA before encoding; the
+/// lossless encoder compresses constant alpha and small numbers of colors
+/// efficiently.
+#[derive(Debug)]
+pub struct WebPEncoder<T: PixelValue> {
+    width: u32,
+    height: u32,
+    pixels: Pixels<T>,
+    opacity: u8,
+}
+
+impl<T: PixelValue> WebPEncoder<T> {
+    pub fn new(width: u32, height: u32, pixels: Pixels<T>) -> WebPEncoder<T> {
+        WebPEncoder {
+            width,
+            height,
+            pixels,
+            opacity: 255u8,
+        }
+    }
+
+    /// Set opacity that is multiplied with the alpha of every pixel
+    pub fn with_opacity(mut self, opacity: u8) -> WebPEncoder<T> {
+        self.opacity = opacity;
+        self
+    }
+
+    fn encode_rgba(&self, rgba: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut webp_buffer: Vec<u8> = Vec::new();
+        image_webp::WebPEncoder::new(&mut webp_buffer).encode(
+            rgba,
+            self.width,
+            self.height,
+            ColorType::Rgba8,
+        )?;
+
+        Ok(webp_buffer)
+    }
+}
+
+impl<T: PixelValue> Encode<T> for WebPEncoder<T> {
+    fn encode(&self, buffer: &[T]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.encode_rgba(&self.pixels.to_rgba(buffer, self.opacity))
+    }
+
+    fn encode_8bit(&self, buffer: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.encode_rgba(&self.pixels.to_rgba_8bit(buffer, self.opacity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::{ColormapRgb8, Rgb8};
+
+    fn decode(webp_data: &[u8]) -> Vec<u8> {
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(webp_data)).unwrap();
+        assert!(decoder.has_alpha());
+        let mut pixels = vec![0u8; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        pixels
+    }
+
+    #[test]
+    fn test_encode_grayscale() {
+        let encoder =
+            WebPEncoder::<u8>::new(2, 1, Pixels::Grayscale { nodata: 0 }).with_opacity(128);
+        let webp_data = encoder.encode_8bit(&[0, 200]).unwrap();
+        assert_eq!(&webp_data[..4], b"RIFF");
+        assert_eq!(decode(&webp_data), [0, 0, 0, 0, 200, 200, 200, 128]);
+    }
+
+    #[test]
+    fn test_encode_rgb() {
+        let encoder = WebPEncoder::<u8>::new(2, 1, Pixels::rgb(0));
+        let webp_data = encoder.encode_8bit(&[0, 0, 0, 255, 0, 0]).unwrap();
+        assert_eq!(decode(&webp_data), [0, 0, 0, 0, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_encode_colormap() {
+        let mut colormap = ColormapRgb8::<u16>::new(2, 0);
+        colormap.add_color(1000, Rgb8 { r: 255, g: 0, b: 0 });
+        colormap.add_color_alpha(2000, Rgb8 { r: 0, g: 0, b: 255 }, 128);
+
+        let encoder = WebPEncoder::new(4, 1, Pixels::Colormap(colormap));
+        let webp_data = encoder.encode(&[0, 1000, 2000, 3000]).unwrap();
+        assert_eq!(
+            decode(&webp_data),
+            [0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 255, 128, 0, 0, 0, 0]
+        );
+    }
+}
@@ -2,57 +2,355 @@
 
 use std::error::Error;
 // use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
 use crossbeam::channel;
 // use gdal::spatial_ref::SpatialRef;
-use gdal::raster::GdalDataType;
+use gdal::raster::{ColorInterpretation, GdalDataType, GdalType, RasterBand};
 use indicatif::{ProgressBar, ProgressStyle};
 
 // use crate::affine::Affine;
 // use crate::dataset::{write_raster, Dataset};
-use crate::dataset::Dataset;
+use crate::array::{all_nodata, is_nodata};
+use crate::bounds::Bounds;
+use crate::cutline::{Cutline, Overlap};
+use crate::dataset::{geo_to_tms_bounds, Dataset};
+use crate::directory::{DirectoryWriter, TileScheme};
+use crate::geopackage::GeoPackage;
 use crate::mbtiles::MBTiles;
-use crate::png::{ColormapEncoder, Encode, GrayscaleEncoder, RGBEncoder, Rgb8};
+use crate::pmtiles::PMTiles;
+use crate::png::{
+    colormap_encoder, grayscale_encoder, multiply_alpha, parse_colormap, read_colormap_file,
+    rgb_encoder, rgba_encoder, ClassEdges, ClassifiedColormap, ColorRamp, ColormapEncoder,
+    ColormapEntry, ColormapRgb8, DataEncoder, DataValue, ElevationEncoding, Encode,
+    Grayscale16Encoder, Interpolation, LinearStretch, Quantizer, Rgb8, TileFormat,
+};
 use crate::tileid::{TileID, TileRange};
+use crate::tilejson::TileJSON;
+use crate::tms::{TileMatrixSet, ZoomLevel};
+use crate::writer::{OutputType, TileWriter};
 
 pub fn render_tiles(
     tiff: &PathBuf,
-    mbtiles: &PathBuf,
-    minzoom: u8,
-    maxzoom: u8,
+    output: &PathBuf,
+    minzoom: ZoomLevel,
+    maxzoom: ZoomLevel,
     tilesize: u16,
+    format: TileFormat,
+    scheme: TileScheme,
+    tms: &TileMatrixSet,
+    bounds: Option<Bounds>,
+    cutline: &Option<PathBuf>,
     name: &Option<String>,
     description: &Option<String>,
     attribution: &Option<String>,
+    tilejson: &Option<PathBuf>,
+    tile_url: &Option<String>,
     workers: u8,
     colormap: &Option<String>,
+    colormap_file: &Option<PathBuf>,
+    min: Option<f64>,
+    max: Option<f64>,
+    ramp: &Option<String>,
+    ramp_palette: bool,
+    ramp_interpolation: Interpolation,
+    classes: &Option<String>,
+    class_edges: ClassEdges,
+    elevation: Option<ElevationEncoding>,
+    rgb: bool,
+    gray16: bool,
+    no_color_table: bool,
+    quantizer: Option<Quantizer>,
+    opacity: u8,
     disable_overviews: bool,
 ) -> Result<()> {
     let dataset = Dataset::open(tiff, false).unwrap();
     let band = dataset.band(1).unwrap();
     let dtype = band.band_type();
-    let geo_bounds = dataset.geo_bounds().unwrap();
-    let mercator_bounds = dataset.mercator_bounds().unwrap();
+    let dataset_geo_bounds = dataset.geo_bounds().unwrap();
 
-    if colormap.is_some() && dtype != GdalDataType::UInt8 {
-        return Err(anyhow!("colormap can only be provided for uint8 data"));
+    // datasets that cross the antimeridian are split into areas on either side
+    // of it, so that tiles are only rendered for those areas
+    let mut geo_areas = dataset_geo_bounds.split_antimeridian();
+    let mut tms_areas = match dataset_geo_bounds.crosses_antimeridian() {
+        false => vec![dataset.tms_bounds(tms).unwrap()],
+        true => geo_areas
+            .iter()
+            .map(|area| geo_to_tms_bounds(area, tms))
+            .collect::<Result<Vec<Bounds>, _>>()
+            .map_err(|e| anyhow!("could not transform bounds: {}", e))?,
+    };
+
+    // restrict tiles and metadata to the intersection with bounds, and clip
+    // pixels outside bounds within those tiles; bounds that cross the
+    // antimeridian are split like the dataset, with a clip for each side
+    let clip = match bounds {
+        Some(b) => {
+            let clip_areas = b.split_antimeridian();
+            geo_areas = Bounds::intersections(&geo_areas, &clip_areas);
+            if geo_areas.is_empty() {
+                return Err(anyhow!("bounds do not overlap the dataset"));
+            }
+
+            // limit to latitudes of the dataset, which are valid in the CRS of
+            // the tile matrix set
+            let clip = clip_areas
+                .iter()
+                .map(|c| {
+                    geo_to_tms_bounds(
+                        &Bounds {
+                            ymin: c.ymin.max(dataset_geo_bounds.ymin),
+                            ymax: c.ymax.min(dataset_geo_bounds.ymax),
+                            ..*c
+                        },
+                        tms,
+                    )
+                })
+                .collect::<Result<Vec<Bounds>, _>>()
+                .map_err(|e| anyhow!("could not transform bounds: {}", e))?;
+            tms_areas = Bounds::intersections(&tms_areas, &clip);
+            clip
+        }
+        None => Vec::new(),
+    };
+
+    // tiles that do not overlap the cutline are skipped, and pixels outside the
+    // cutline are clipped within those that do
+    let cutline = match cutline {
+        Some(path) => {
+            let cutline =
+                Cutline::read(path, tms).map_err(|e| anyhow!("invalid cutline: {}", e))?;
+            tms_areas = tms_areas
+                .iter()
+                .filter_map(|area| area.intersection(&cutline.bounds))
+                .collect();
+            if tms_areas.is_empty() {
+                return Err(anyhow!("cutline does not overlap the dataset"));
+            }
+            Some(cutline)
+        }
+        None => None,
+    };
+
+    if tms_areas.is_empty() {
+        return Err(anyhow!("bounds do not overlap the dataset"));
+    }
+
+    // bounds that cross the antimeridian have xmin greater than xmax
+    let geo_bounds = Bounds::join_antimeridian(&geo_areas);
+
+    // automatic zoom levels are based on the resolution of the dataset warped
+    // to the CRS of the tile matrix set
+    let (minzoom, maxzoom) = match (minzoom, maxzoom) {
+        (ZoomLevel::Level(minzoom), ZoomLevel::Level(maxzoom)) => (minzoom, maxzoom),
+        _ => {
+            let (xres, yres) = dataset
+                .tms_vrt(tms, &[], None)
+                .and_then(|vrt| vrt.resolution())
+                .map_err(|e| anyhow!("could not determine resolution of dataset: {}", e))?;
+
+            let maxzoom = match maxzoom {
+                ZoomLevel::Level(zoom) => zoom,
+                ZoomLevel::Auto => tms.zoom_for_resolution(xres.min(yres), tilesize),
+            };
+
+            let minzoom = match minzoom {
+                ZoomLevel::Level(zoom) => zoom,
+                ZoomLevel::Auto => {
+                    let extent = tms_areas
+                        .iter()
+                        .map(|area| (area.xmax - area.xmin).max(area.ymax - area.ymin))
+                        .fold(0., f64::max);
+                    tms.zoom_for_resolution(extent / tilesize as f64, tilesize)
+                        .min(maxzoom)
+                }
+            };
+
+            if minzoom > maxzoom {
+                return Err(anyhow!(
+                    "minzoom ({}) must be less than automatic maxzoom ({})",
+                    minzoom,
+                    maxzoom
+                ));
+            }
+
+            (minzoom, maxzoom)
+        }
+    };
+
+    if maxzoom > tms.maxzoom() {
+        return Err(anyhow!(
+            "maxzoom must not exceed the maximum zoom level of the tile matrix set ({})",
+            tms.maxzoom()
+        ));
+    }
+
+    if rgb {
+        if dataset.band_count() < 3 {
+            return Err(anyhow!("RGB rendering requires at least 3 bands"));
+        }
+
+        // band 4 is only read if it is an alpha band
+        let has_alpha =
+            has_alpha_band(&dataset).map_err(|e| anyhow!("could not read alpha band: {}", e))?;
+        let band_count = match has_alpha {
+            true => 4,
+            false => 3,
+        };
+        for i in 1..=band_count {
+            if dataset.band(i).unwrap().band_type() != GdalDataType::UInt8 {
+                return Err(anyhow!("RGB rendering requires uint8 bands"));
+            }
+        }
+    }
+
+    if (colormap.is_some() || colormap_file.is_some())
+        && !matches!(
+            dtype,
+            GdalDataType::UInt8 | GdalDataType::UInt16 | GdalDataType::Int16 | GdalDataType::UInt32
+        )
+    {
+        return Err(anyhow!(
+            "colormap can only be provided for uint8, uint16, int16, or uint32 data"
+        ));
+    }
+
+    // parse colormap entries and check that values are valid for the data type
+    // before starting workers
+    let colormap = match (colormap, colormap_file) {
+        (Some(c), _) => {
+            let entries = parse_colormap(c).map_err(|e| anyhow!("invalid colormap: {}", e))?;
+            check_colormap(&entries, dtype).map_err(|e| anyhow!("invalid colormap: {}", e))?;
+            Some(entries)
+        }
+        (_, Some(f)) => {
+            let entries =
+                read_colormap_file(f).map_err(|e| anyhow!("invalid colormap file: {}", e))?;
+            check_colormap(&entries, dtype)
+                .map_err(|e| anyhow!("invalid colormap file {:?}: {}", f, e))?;
+            Some(entries)
+        }
+        _ => None,
+    };
+
+    // opacity is applied to alpha of each colormap entry
+    let colormap = colormap.map(|mut entries| {
+        for entry in entries.iter_mut() {
+            entry.alpha = multiply_alpha(entry.alpha, opacity);
+        }
+        entries
+    });
+
+    if quantizer.is_some() && (dtype != GdalDataType::UInt32 || colormap.is_some()) {
+        return Err(anyhow!(
+            "quantize can only be used for uint32 data without a colormap"
+        ));
+    }
+
+    if gray16 && dtype != GdalDataType::UInt16 {
+        return Err(anyhow!("gray16 can only be used for uint16 data"));
     }
 
-    if !matches!(dtype, GdalDataType::UInt8 | GdalDataType::UInt32) {
+    // paletted uint8 data without NODATA need a value that is not used by the
+    // color table for pixels outside the dataset
+    if dtype == GdalDataType::UInt8
+        && colormap.is_none()
+        && !no_color_table
+        && band.no_data_value().is_none()
+        && band.color_interpretation() == ColorInterpretation::PaletteIndex
+        && band.color_table().is_some()
+        && unused_color_table_index(&band).is_none()
+    {
+        return Err(anyhow!(
+            "color table of band 1 uses all 256 values and band 1 does not have a NODATA value; set a NODATA value or use --no-color-table"
+        ));
+    }
+
+    let is_float = matches!(dtype, GdalDataType::Float32 | GdalDataType::Float64);
+
+    // validate color ramp before starting workers
+    let ramp = match ramp {
+        Some(r) => Some(
+            ColorRamp::parse(r)
+                .map_err(|e| anyhow!("invalid color ramp: {}", e))?
+                .with_interpolation(ramp_interpolation),
+        ),
+        _ => None,
+    };
+
+    // validate classes before starting workers
+    let classes = match classes {
+        Some(c) => Some(
+            ClassifiedColormap::parse(c, class_edges)
+                .map_err(|e| anyhow!("invalid classes: {}", e))?,
+        ),
+        _ => None,
+    };
+
+    if !matches!(
+        dtype,
+        GdalDataType::UInt8
+            | GdalDataType::UInt16
+            | GdalDataType::Int16
+            | GdalDataType::UInt32
+            | GdalDataType::Float32
+            | GdalDataType::Float64
+    ) {
         return Err(anyhow!(format!(
             "data type is not supported: {:}",
             dtype.name()
         )));
     }
 
+    // values are stretched between the range of the color ramp if provided,
+    // otherwise detect range of values for stretching 16-bit and float data
+    // to 8-bit grayscale if not provided
+    let stretch = match (dtype, &ramp, min, max) {
+        _ if elevation.is_some()
+            || rgb
+            || colormap.is_some()
+            || classes.is_some()
+            || gray16
+            || format == TileFormat::Data =>
+        {
+            None
+        }
+        (_, Some(r), _, _) => Some(LinearStretch::new(r.min(), r.max())),
+        (GdalDataType::UInt8 | GdalDataType::UInt32, _, _, _) => None,
+        (_, _, Some(min), Some(max)) => Some(LinearStretch::new(min, max)),
+        _ => {
+            let stats = band.compute_raster_min_max(false)?;
+            Some(LinearStretch::new(
+                min.unwrap_or(stats.min),
+                max.unwrap_or(stats.max),
+            ))
+        }
+    };
+
+    // grayscale PNG tiles of uint8 data are packed at the bit depth that holds
+    // the maximum value of the dataset, so that it is the same for all tiles
+    let gray_max = match dtype {
+        GdalDataType::UInt8
+            if matches!(format, TileFormat::Png | TileFormat::Mixed { .. })
+                && stretch.is_none()
+                && colormap.is_none()
+                && classes.is_none()
+                && elevation.is_none()
+                && !rgb
+                && (no_color_table
+                    || band.color_interpretation() != ColorInterpretation::PaletteIndex) =>
+        {
+            Some(band.compute_raster_min_max(false)?.max as u8)
+        }
+        _ => None,
+    };
+
     let mut metadata = Vec::<(&str, &str)>::new();
 
-    // default tileset name to output filename
     let name = match name {
         Some(n) => n.to_owned(),
-        None => String::from(mbtiles.file_stem().unwrap().to_str().unwrap()),
+        None => default_name(output, tiff),
     };
     metadata.push(("name", &name));
 
@@ -70,30 +368,96 @@ pub fn render_tiles(
     metadata.push(("minzoom", &minzoom_str));
     metadata.push(("maxzoom", &maxzoom_str));
 
-    let bounds_str = format!(
-        "{:.5},{:.5},{:.5},{:.5}",
-        geo_bounds.xmin, geo_bounds.ymin, geo_bounds.xmax, geo_bounds.ymax
-    );
+    // bounds that cross the antimeridian are written as the full range of
+    // longitudes for clients that do not support xmin greater than xmax, and
+    // separately as adjusted bounds
+    let bounds_to_str =
+        |b: &Bounds| format!("{:.5},{:.5},{:.5},{:.5}", b.xmin, b.ymin, b.xmax, b.ymax);
+    let bounds_str = bounds_to_str(&geo_bounds.expand_antimeridian());
     metadata.push(("bounds", &bounds_str));
 
-    let center_str = format!(
-        "{:.5},{:.5},{}",
-        (geo_bounds.xmax + geo_bounds.xmin) / 2.,
-        (geo_bounds.ymax + geo_bounds.ymin) / 2.,
-        minzoom
-    );
+    let adjusted_bounds_str = bounds_to_str(&geo_bounds);
+    if geo_bounds.xmin > geo_bounds.xmax {
+        metadata.push(("antimeridian_adjusted_bounds", &adjusted_bounds_str));
+    }
+
+    let (center_x, center_y) = geo_bounds.center();
+    let center_str = format!("{:.5},{:.5},{}", center_x, center_y, minzoom);
     metadata.push(("center", &center_str));
 
     metadata.push(("type", "overlay"));
-    metadata.push(("format", "png"));
+    metadata.push(("format", format.name()));
+
+    // mixed tilesets may include tiles in any of these formats
+    if format.formats() != format.name() {
+        metadata.push(("formats", format.formats()));
+    }
+
+    if let Some(e) = &elevation {
+        metadata.push(("encoding", e.format.name()));
+    }
+
+    // tiles are assumed to be WebMercatorQuad unless otherwise specified
+    if !tms.is_web_mercator() {
+        metadata.push(("tile_matrix_set", &tms.id));
+        metadata.push(("crs", &tms.crs));
+    }
+
+    // data tiles are described so that clients can decode them; NaN is always
+    // treated as NODATA for float data
+    let dtype_str = match dtype {
+        GdalDataType::UInt8 => "uint8",
+        GdalDataType::UInt16 => "uint16",
+        GdalDataType::Int16 => "int16",
+        GdalDataType::UInt32 => "uint32",
+        GdalDataType::Float32 => "float32",
+        _ => "float64",
+    };
+    let tilesize_str = format!("{}", tilesize);
+    let data_nodata = band
+        .no_data_value()
+        .unwrap_or(if is_float { f64::NAN } else { 0. });
+    let data_nodata_str = format!("{}", data_nodata);
+    if format == TileFormat::Data {
+        metadata.push(("dtype", dtype_str));
+        metadata.push(("width", &tilesize_str));
+        metadata.push(("height", &tilesize_str));
+        metadata.push(("nodata", &data_nodata_str));
+        metadata.push(("byte_order", "little-endian"));
+        metadata.push(("compression", "zstd"));
+    }
+
+    // 16-bit grayscale tiles store values, so clients need NODATA to decode them
+    if gray16 {
+        metadata.push(("nodata", &data_nodata_str));
+    }
+
     metadata.push(("version", "1.0.0"));
 
     // close dataset; will be opened in each thread
     drop(dataset);
 
+    let output_type = OutputType::from_path(output);
+
     // in a block so that connections are dropped to force flush / close
     {
-        let db = MBTiles::new(mbtiles, workers).unwrap();
+        let db: Box<dyn TileWriter> = match output_type {
+            OutputType::MBTiles => Box::new(MBTiles::new(output, workers).unwrap()),
+            OutputType::PMTiles => {
+                Box::new(PMTiles::new(output, format, minzoom, maxzoom, &geo_bounds)?)
+            }
+            OutputType::GeoPackage => Box::new(GeoPackage::new(
+                output,
+                workers,
+                format,
+                tilesize,
+                minzoom,
+                maxzoom,
+                // GeoPackage output is only supported for WebMercatorQuad
+                &Bounds::union(&tms_areas),
+            )?),
+            OutputType::Directory => Box::new(DirectoryWriter::new(output, format, scheme)?),
+        };
         db.set_metadata(&metadata).unwrap();
 
         let (snd, rcv) = channel::bounded(1);
@@ -101,18 +465,33 @@ pub fn render_tiles(
         crossbeam::scope(|s| {
             // add tiles to queue
             s.spawn(|_| {
-                let mut tiles: TileRange;
+                let mut ranges: Vec<TileRange>;
                 for zoom in minzoom..(maxzoom + 1) {
-                    tiles = TileRange::new(zoom, &mercator_bounds);
-                    let bar = ProgressBar::new(tiles.count() as u64)
+                    ranges = tms_areas
+                        .iter()
+                        .map(|area| tms.tile_range(zoom, area))
+                        .collect();
+                    let bar = ProgressBar::new(ranges.iter().map(|r| r.count() as u64).sum())
                         .with_style(ProgressStyle::default_bar().template(
                             "{prefix:<8} {bar:50} {pos}/{len} {msg} [elapsed: {elapsed_precise}]]",
                         ).unwrap())
                         .with_prefix(format!("zoom: {}", zoom));
 
-                    for tile_id in tiles.iter() {
-                        snd.send(tile_id).unwrap();
-                        bar.inc(1);
+                    for (i, tiles) in ranges.iter().enumerate() {
+                        for tile_id in tiles.iter() {
+                            // areas on either side of the antimeridian may
+                            // share tiles at low zoom levels
+                            let rendered = ranges[..i].iter().any(|r| r.contains(&tile_id));
+
+                            if !rendered
+                                && cutline.as_ref().is_none_or(|c| {
+                                    c.relate(&tms.tile_bounds(&tile_id)) != Overlap::Disjoint
+                                })
+                            {
+                                snd.send(tile_id).unwrap();
+                            }
+                            bar.inc(1);
+                        }
                     }
 
                     bar.finish();
@@ -122,19 +501,290 @@ pub fn render_tiles(
             });
 
             let tiff = &tiff;
-            let db = &db;
+            let clip = clip.as_slice();
+            let cutline = cutline.as_ref();
+            let db = db.as_ref();
             let colormap = &colormap;
+            let ramp = &ramp;
+            let classes = &classes;
             for _ in 0..workers {
                 let rcv = rcv.clone();
 
                 s.spawn(move |_| {
+                    if format == TileFormat::Data {
+                        match dtype {
+                            GdalDataType::UInt8 => worker_data::<u8>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                data_nodata,
+                                disable_overviews,
+                            ),
+                            GdalDataType::UInt16 => worker_data::<u16>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                data_nodata,
+                                disable_overviews,
+                            ),
+                            GdalDataType::Int16 => worker_data::<i16>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                data_nodata,
+                                disable_overviews,
+                            ),
+                            GdalDataType::UInt32 => worker_data::<u32>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                data_nodata,
+                                disable_overviews,
+                            ),
+                            GdalDataType::Float32 => worker_data::<f32>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                data_nodata,
+                                disable_overviews,
+                            ),
+                            GdalDataType::Float64 => worker_data::<f64>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                data_nodata,
+                                disable_overviews,
+                            ),
+                            // supported data types validated above
+                            _ => {
+                                unreachable!("data type not supported");
+                            }
+                        }
+                        .unwrap();
+                        return;
+                    }
+
+                    if rgb {
+                        worker_rgb(
+                            rcv,
+                            tiff,
+                            tms,
+                            clip,
+                            cutline,
+                            db,
+                            tilesize,
+                            format,
+                            opacity,
+                            disable_overviews,
+                        )
+                        .unwrap();
+                        return;
+                    }
+
+                    if gray16 {
+                        worker_gray16(
+                            rcv,
+                            tiff,
+                            tms,
+                            clip,
+                            cutline,
+                            db,
+                            tilesize,
+                            data_nodata as u16,
+                            disable_overviews,
+                        )
+                        .unwrap();
+                        return;
+                    }
+
+                    // elevation data of any type are read as float64
+                    if let Some(elevation) = elevation {
+                        worker_elevation(
+                            rcv,
+                            tiff,
+                            tms,
+                            clip,
+                            cutline,
+                            db,
+                            tilesize,
+                            format,
+                            elevation,
+                            disable_overviews,
+                        )
+                        .unwrap();
+                        return;
+                    }
+
+                    // classified data of any type are read as float64
+                    if let Some(classes) = classes {
+                        worker_classes(
+                            rcv,
+                            tiff,
+                            tms,
+                            clip,
+                            cutline,
+                            db,
+                            tilesize,
+                            format,
+                            classes,
+                            opacity,
+                            disable_overviews,
+                        )
+                        .unwrap();
+                        return;
+                    }
+
+                    // color ramps for integer data are rendered by reading
+                    // values as float64
+                    if ramp.is_some() && !is_float {
+                        worker_float::<f64>(
+                            rcv,
+                            tiff,
+                            tms,
+                            clip,
+                            cutline,
+                            db,
+                            tilesize,
+                            format,
+                            stretch.unwrap(),
+                            ramp,
+                            ramp_palette,
+                            opacity,
+                            disable_overviews,
+                        )
+                        .unwrap();
+                        return;
+                    }
+
                     match dtype {
                         GdalDataType::UInt8 => {
-                            worker_u8(rcv, tiff, db, tilesize, colormap, disable_overviews)
-                                .unwrap();
+                            worker_u8(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                format,
+                                colormap,
+                                !no_color_table,
+                                gray_max,
+                                opacity,
+                                disable_overviews,
+                            )
+                            .unwrap();
+                        }
+                        GdalDataType::UInt16 => {
+                            worker_u16(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                format,
+                                colormap,
+                                stretch,
+                                opacity,
+                                disable_overviews,
+                            )
+                            .unwrap();
+                        }
+                        GdalDataType::Int16 => {
+                            worker_i16(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                format,
+                                colormap,
+                                stretch,
+                                opacity,
+                                disable_overviews,
+                            )
+                            .unwrap();
                         }
                         GdalDataType::UInt32 => {
-                            worker_u32(rcv, tiff, db, tilesize, disable_overviews).unwrap();
+                            worker_u32(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                format,
+                                colormap,
+                                quantizer,
+                                opacity,
+                                disable_overviews,
+                            )
+                            .unwrap();
+                        }
+                        GdalDataType::Float32 => {
+                            worker_float::<f32>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                format,
+                                stretch.unwrap(),
+                                ramp,
+                                ramp_palette,
+                                opacity,
+                                disable_overviews,
+                            )
+                            .unwrap();
+                        }
+                        GdalDataType::Float64 => {
+                            worker_float::<f64>(
+                                rcv,
+                                tiff,
+                                tms,
+                                clip,
+                                cutline,
+                                db,
+                                tilesize,
+                                format,
+                                stretch.unwrap(),
+                                ramp,
+                                ramp_palette,
+                                opacity,
+                                disable_overviews,
+                            )
+                            .unwrap();
                         }
                         // supported data types validated above
                         _ => {
@@ -146,11 +796,29 @@ pub fn render_tiles(
         })
         .unwrap();
 
-        db.update_index().unwrap();
+        db.finish().unwrap();
     }
 
     // change the database back to non-WAL mode
-    MBTiles::flush(mbtiles).unwrap();
+    match output_type {
+        OutputType::MBTiles => MBTiles::flush(output).unwrap(),
+        OutputType::GeoPackage => GeoPackage::flush(output).unwrap(),
+        _ => {}
+    }
+
+    if let (Some(path), Some(url)) = (tilejson, tile_url) {
+        TileJSON {
+            tile_url: url,
+            name: &name,
+            description: description.as_deref(),
+            attribution: attribution.as_deref(),
+            minzoom,
+            maxzoom,
+            bounds: &geo_bounds,
+            scheme: scheme.name(),
+        }
+        .write(path)?;
+    }
 
     Ok(())
 }
@@ -158,46 +826,420 @@ pub fn render_tiles(
 fn worker_u8(
     tiles: channel::Receiver<TileID>,
     tiff_filename: &PathBuf,
-    db: &MBTiles,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
     tilesize: u16,
-    colormap_str: &Option<String>,
+    format: TileFormat,
+    colormap_entries: &Option<Vec<ColormapEntry>>,
+    use_color_table: bool,
+    max_value: Option<u8>,
+    opacity: u8,
     disable_overviews: bool,
 ) -> Result<(), Box<dyn Error>> {
     let dataset = Dataset::open(tiff_filename, disable_overviews)?;
-    let vrt = dataset.mercator_vrt()?;
+    let source_band = dataset.band(1)?;
+
+    // paletted data without NODATA use a value that is not used by the color
+    // table for pixels outside the dataset, which are marked by an alpha band
+    let palette_nodata = match source_band.no_data_value() {
+        None if colormap_entries.is_none()
+            && use_color_table
+            && source_band.color_interpretation() == ColorInterpretation::PaletteIndex =>
+        {
+            unused_color_table_index(&source_band)
+        }
+        _ => None,
+    };
+    let (vrt, alpha_index) = match palette_nodata {
+        Some(_) => (
+            dataset.tms_alpha_vrt(tms, clip, cutline)?,
+            Some(dataset.band_count() + 1),
+        ),
+        None => (dataset.tms_vrt(tms, clip, cutline)?, None),
+    };
     let band = vrt.band(1)?;
-    let nodata = band.no_data_value().unwrap() as u8;
+    let alpha_band = alpha_index.map(|i| vrt.band(i)).transpose()?;
 
-    let conn = db.get_connection()?;
+    // pixels outside the dataset are filled with 0 if it does not have NODATA
+    let nodata = palette_nodata
+        .or(band.no_data_value().map(|v| v as u8))
+        .unwrap_or(0u8);
 
     let width: u32 = tilesize as u32;
     let height: u32 = width;
 
-    let (has_colormap, encoder): (bool, Box<dyn Encode<u8>>) = match colormap_str {
-        Some(c) => (
+    // color table is read from the source band because it is not guaranteed
+    // to be carried over to the VRT
+    let color_table = if use_color_table {
+        colormap_from_color_table(&dataset.band(1)?, nodata).map(|mut colormap| {
+            colormap.apply_opacity(opacity);
+            colormap
+        })
+    } else {
+        None
+    };
+
+    let (has_colormap, encoder): (bool, Box<dyn Encode<u8>>) = match (colormap_entries, color_table)
+    {
+        (Some(entries), _) => (
             true,
-            Box::new(ColormapEncoder::<u8>::from_str(width, height, c, nodata).unwrap()),
+            colormap_encoder(
+                format,
+                width,
+                height,
+                ColormapRgb8::<u8>::from_entries(entries, nodata)?,
+            ),
         ),
+        (_, Some(colormap)) => (true, colormap_encoder(format, width, height, colormap)),
         _ => (
             false,
-            Box::new(GrayscaleEncoder::new(width, height, nodata)),
+            grayscale_encoder(format, width, height, nodata, max_value, opacity),
         ),
     };
 
     // create buffers to receive data; these are automatically filled with
     // the appropriate nodata value before reading from the raster
     let mut buffer = vec![0u8; tilesize as usize * tilesize as usize];
+    let mut alpha_buffer = vec![0u8; tilesize as usize * tilesize as usize];
 
     let mut png_data: Vec<u8>;
 
     for tile_id in tiles.iter() {
-        if vrt.read_tile(&band, tile_id, tilesize, &mut buffer, nodata)? {
+        let tile_bounds = tms.tile_bounds(&tile_id);
+        if vrt.read_tile(&band, &tile_bounds, tilesize, &mut buffer, nodata)? {
+            if let Some(alpha_band) = &alpha_band {
+                vrt.read_tile(alpha_band, &tile_bounds, tilesize, &mut alpha_buffer, 0u8)?;
+                for (value, &alpha) in buffer.iter_mut().zip(alpha_buffer.iter()) {
+                    if alpha == 0 {
+                        *value = nodata;
+                    }
+                }
+
+                if all_nodata(&buffer, nodata) {
+                    continue;
+                }
+            }
+
             if has_colormap {
                 png_data = encoder.encode(&buffer)?;
             } else {
                 png_data = encoder.encode_8bit(&buffer)?;
             }
-            db.write_tile(&conn, &tile_id, &png_data)?;
+            db.write_tile(&tile_id, &png_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Default tileset name to output filename, or to input filename if output is
+/// a directory without a final path component, e.g., "./"
+fn default_name(output: &Path, tiff: &Path) -> String {
+    output
+        .file_stem()
+        .or_else(|| tiff.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Check if band 4 of dataset is an alpha band
+fn has_alpha_band(dataset: &Dataset) -> Result<bool, Box<dyn Error>> {
+    Ok(dataset.band_count() >= 4
+        && dataset.band(4)?.color_interpretation() == ColorInterpretation::AlphaBand)
+}
+
+/// Check that colormap entries can be converted to the data type, as is done
+/// by each worker, so that invalid entries are reported before rendering
+fn check_colormap(entries: &[ColormapEntry], dtype: GdalDataType) -> Result<(), Box<dyn Error>> {
+    match dtype {
+        GdalDataType::UInt8 => ColormapRgb8::<u8>::from_entries(entries, 0).map(|_| ()),
+        GdalDataType::UInt16 => ColormapRgb8::<u16>::from_entries(entries, 0).map(|_| ()),
+        GdalDataType::Int16 => ColormapRgb8::<i16>::from_entries(entries, 0).map(|_| ()),
+        GdalDataType::UInt32 => ColormapRgb8::<u32>::from_entries(entries, 0).map(|_| ()),
+        _ => Err("colormap can only be provided for uint8, uint16, int16, or uint32 data".into()),
+    }
+}
+
+/// Return the first value that is not used by the color table of band, either
+/// because it is beyond the entries of the table or its entry is fully
+/// transparent, or None if the table uses all 256 values
+fn unused_color_table_index(band: &RasterBand) -> Option<u8> {
+    let color_table = band.color_table()?;
+    let num_entries = color_table.entry_count();
+    (0..256usize)
+        .find(|&i| i >= num_entries || color_table.entry_as_rgb(i).is_none_or(|entry| entry.a == 0))
+        .map(|i| i as u8)
+}
+
+/// Create colormap from the color table of band if it has a palette color
+/// interpretation.  Fully transparent entries are omitted and rendered as
+/// NODATA.
+fn colormap_from_color_table(band: &RasterBand, nodata: u8) -> Option<ColormapRgb8<u8>> {
+    if band.color_interpretation() != ColorInterpretation::PaletteIndex {
+        return None;
+    }
+
+    let color_table = band.color_table()?;
+    let num_entries = color_table.entry_count().min(256);
+    let mut colormap = ColormapRgb8::<u8>::new(num_entries, nodata);
+
+    for i in 0..num_entries {
+        if i == nodata as usize {
+            continue;
+        }
+
+        if let Some(entry) = color_table.entry_as_rgb(i)
+            && entry.a > 0
+        {
+            colormap.add_color_alpha(
+                i as u8,
+                Rgb8 {
+                    r: entry.r.clamp(0, 255) as u8,
+                    g: entry.g.clamp(0, 255) as u8,
+                    b: entry.b.clamp(0, 255) as u8,
+                },
+                entry.a.clamp(0, 255) as u8,
+            );
+        }
+    }
+
+    Some(colormap)
+}
+
+fn worker_rgb(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    format: TileFormat,
+    opacity: u8,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    // use band 4 if it is an alpha band, otherwise add an alpha band after all
+    // bands that is transparent outside the dataset and for NODATA pixels
+    let (vrt, alpha_index) = match has_alpha_band(&dataset)? {
+        true => (dataset.tms_vrt(tms, clip, cutline)?, 4),
+        false => (
+            dataset.tms_alpha_vrt(tms, clip, cutline)?,
+            dataset.band_count() + 1,
+        ),
+    };
+    let bands = [vrt.band(1)?, vrt.band(2)?, vrt.band(3)?];
+    let alpha_band = vrt.band(alpha_index)?;
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    let encoder: Box<dyn Encode<u8>> = rgba_encoder(format, width, height, opacity);
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut alpha_buffer = vec![0u8; buffer_size];
+    let mut band_buffer = vec![0u8; buffer_size];
+    let mut rgba_buffer: Vec<u8> = vec![0u8; buffer_size * 4];
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        let tile_bounds = tms.tile_bounds(&tile_id);
+
+        // pixels outside the dataset are filled with 0 (transparent)
+        if !vrt.read_tile(&alpha_band, &tile_bounds, tilesize, &mut alpha_buffer, 0u8)? {
+            continue;
+        }
+
+        for (band_index, band) in bands.iter().enumerate() {
+            // bands may be entirely 0 where alpha is not
+            vrt.read_tile(band, &tile_bounds, tilesize, &mut band_buffer, 0u8)?;
+
+            for (i, &value) in band_buffer.iter().enumerate() {
+                // set color of fully transparent pixels to 0 for better compression
+                rgba_buffer[i * 4 + band_index] = if alpha_buffer[i] == 0 { 0 } else { value };
+            }
+        }
+
+        for (i, &alpha) in alpha_buffer.iter().enumerate() {
+            rgba_buffer[i * 4 + 3] = alpha;
+        }
+
+        png_data = encoder.encode_8bit(&rgba_buffer)?;
+        db.write_tile(&tile_id, &png_data)?;
+    }
+
+    Ok(())
+}
+
+fn worker_u16(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    format: TileFormat,
+    colormap_entries: &Option<Vec<ColormapEntry>>,
+    stretch: Option<LinearStretch>,
+    opacity: u8,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+    // pixels outside the dataset are filled with 0 if it does not have NODATA
+    let nodata = band.no_data_value().unwrap_or(0.) as u16;
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    let colormap_encoder: Option<Box<dyn Encode<u16>>> = match colormap_entries {
+        Some(entries) => Some(colormap_encoder(
+            format,
+            width,
+            height,
+            ColormapRgb8::<u16>::from_entries(entries, nodata)?,
+        )),
+        _ => None,
+    };
+
+    // stretched values are 1-255; 0 is reserved for NODATA
+    let encoder: Box<dyn Encode<u8>> = grayscale_encoder(format, width, height, 0u8, None, opacity);
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut buffer = vec![nodata; buffer_size];
+    let mut gray_buffer: Vec<u8> = vec![0u8; buffer_size];
+
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            png_data = match (&colormap_encoder, stretch) {
+                (Some(encoder), _) => encoder.encode(&buffer)?,
+                (None, Some(stretch)) => {
+                    stretch.stretch(&buffer, nodata, &mut gray_buffer);
+                    encoder.encode_8bit(&gray_buffer)?
+                }
+                // stretch is always provided if colormap is not
+                _ => unreachable!("stretch not provided"),
+            };
+            db.write_tile(&tile_id, &png_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn worker_gray16(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    nodata: u16,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    let encoder = Grayscale16Encoder::new(width, height, nodata);
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut buffer = vec![nodata; buffer_size];
+
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            png_data = encoder.encode(&buffer)?;
+            db.write_tile(&tile_id, &png_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn worker_i16(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    format: TileFormat,
+    colormap_entries: &Option<Vec<ColormapEntry>>,
+    stretch: Option<LinearStretch>,
+    opacity: u8,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+    // pixels outside the dataset are filled with 0 if it does not have NODATA
+    let nodata = band.no_data_value().unwrap_or(0.) as i16;
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    let colormap_encoder: Option<Box<dyn Encode<i16>>> = match colormap_entries {
+        Some(entries) => Some(colormap_encoder(
+            format,
+            width,
+            height,
+            ColormapRgb8::<i16>::from_entries(entries, nodata)?,
+        )),
+        _ => None,
+    };
+
+    // stretched values are 1-255; 0 is reserved for NODATA
+    let encoder: Box<dyn Encode<u8>> = grayscale_encoder(format, width, height, 0u8, None, opacity);
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut buffer = vec![nodata; buffer_size];
+    let mut gray_buffer: Vec<u8> = vec![0u8; buffer_size];
+
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            png_data = match (&colormap_encoder, stretch) {
+                (Some(encoder), _) => encoder.encode(&buffer)?,
+                (None, Some(stretch)) => {
+                    stretch.stretch(&buffer, nodata, &mut gray_buffer);
+                    encoder.encode_8bit(&gray_buffer)?
+                }
+                // stretch is always provided if colormap is not
+                _ => unreachable!("stretch not provided"),
+            };
+            db.write_tile(&tile_id, &png_data)?;
         }
     }
 
@@ -207,38 +1249,71 @@ fn worker_u8(
 fn worker_u32(
     tiles: channel::Receiver<TileID>,
     tiff_filename: &PathBuf,
-    db: &MBTiles,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
     tilesize: u16,
+    format: TileFormat,
+    colormap_entries: &Option<Vec<ColormapEntry>>,
+    quantizer: Option<Quantizer>,
+    opacity: u8,
     disable_overviews: bool,
 ) -> Result<(), Box<dyn Error>> {
     let dataset = Dataset::open(tiff_filename, disable_overviews)?;
-    let vrt = dataset.mercator_vrt()?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
     let band = vrt.band(1)?;
     let nodata = band.no_data_value().unwrap() as u32;
 
-    let conn = db.get_connection()?;
-
     let width: u32 = tilesize as u32;
     let height: u32 = width;
 
-    // get a function pointer for the RGBEncoder that defines specific type of data
-    let rgb_encoder = RGBEncoder::new(width, height, nodata);
-    let encode_rgb = <RGBEncoder as Encode<u32>>::encode_8bit;
+    // if a colormap is provided, values are rendered using it instead of
+    // being treated as packed RGB values
+    let explicit_colormap_encoder: Option<Box<dyn Encode<u32>>> = match colormap_entries {
+        Some(entries) => Some(colormap_encoder(
+            format,
+            width,
+            height,
+            ColormapRgb8::<u32>::from_entries(entries, nodata)?,
+        )),
+        _ => None,
+    };
+
+    let rgb_encoder: Box<dyn Encode<u32>> = rgb_encoder(format, width, height, nodata, opacity);
 
     let mut colormap_encoder: ColormapEncoder<u32> =
         ColormapEncoder::new(width, height, nodata, 256)?;
 
+    // tiles with more unique values than fit in a palette may be quantized;
+    // values are indexes into the quantized palette
+    let quantizer = quantizer.filter(|_| format == TileFormat::Png);
+    let mut quantized_encoder: ColormapEncoder<u8> = ColormapEncoder::new(width, height, 0u8, 255)?;
+
     let buffer_size = tilesize as usize * tilesize as usize;
     let mut buffer = vec![nodata; buffer_size];
     let mut rgb_buffer: Vec<u8> = vec![0u8; buffer_size * 3];
+    let mut mask: Vec<bool> = vec![false; buffer_size];
     let mut color: Rgb8;
     let mut png_data: Vec<u8>;
     let mut use_palette: bool;
 
     for tile_id in tiles.iter() {
-        if vrt.read_tile(&band, tile_id, tilesize, &mut buffer, nodata)? {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            if let Some(encoder) = &explicit_colormap_encoder {
+                png_data = encoder.encode(&buffer)?;
+                db.write_tile(&tile_id, &png_data)?;
+                continue;
+            }
+
             // // DEBUG: write raw data to TIFF for inspection
-            // let tile_bounds = tile_id.mercator_bounds();
+            // let tile_bounds = tms.tile_bounds(&tile_id);
             // let xres = (tile_bounds.xmax - tile_bounds.xmin) as f64 / tilesize as f64;
             // let yres = (tile_bounds.ymax - tile_bounds.ymin) as f64 / tilesize as f64;
             // let transform = Affine::new(xres, 0., tile_bounds.xmin, 0., -yres, tile_bounds.ymax);
@@ -255,7 +1330,14 @@ fn worker_u32(
             // .unwrap();
 
             colormap_encoder.colormap.clear();
-            use_palette = true;
+            // tiles with few unique values are smaller as paletted PNGs;
+            // WebP and JPEG do not support palettes, and mixed tiles are only
+            // encoded as PNG if they are not fully opaque
+            use_palette = match format {
+                TileFormat::Png => true,
+                TileFormat::Mixed { .. } => opacity < 255 || buffer.contains(&nodata),
+                _ => false,
+            };
 
             // convert value buffer to 8-bit RGB buffer, ignoring alpha
             // also build up palette of unique values
@@ -264,21 +1346,39 @@ fn worker_u32(
                 rgb_buffer[i * 3] = color.r;
                 rgb_buffer[i * 3 + 1] = color.g;
                 rgb_buffer[i * 3 + 2] = color.b;
+                mask[i] = value != nodata;
 
-                if colormap_encoder.colormap.len() < 256 {
-                    colormap_encoder.colormap.add_color(value, color);
-                } else {
-                    use_palette = false;
+                if use_palette {
+                    if colormap_encoder.colormap.len() < 256 {
+                        colormap_encoder
+                            .colormap
+                            .add_color_alpha(value, color, opacity);
+                    } else {
+                        use_palette = false;
+                    }
                 }
             }
 
+            let quantized = match quantizer {
+                Some(q) if !use_palette => q.quantize(&rgb_buffer, &mask, width as usize),
+                _ => None,
+            };
+
             if use_palette {
                 png_data = colormap_encoder.encode(&buffer)?;
+            } else if let Some(quantized) = quantized {
+                quantized_encoder.colormap.clear();
+                for (i, &color) in quantized.palette.iter().enumerate() {
+                    quantized_encoder
+                        .colormap
+                        .add_color_alpha(i as u8 + 1, color, opacity);
+                }
+                png_data = quantized_encoder.encode(&quantized.indexes)?;
             } else {
-                png_data = encode_rgb(&rgb_encoder, &rgb_buffer)?;
+                png_data = rgb_encoder.encode_8bit(&rgb_buffer)?;
             }
 
-            db.write_tile(&conn, &tile_id, &png_data)?;
+            db.write_tile(&tile_id, &png_data)?;
 
             // DEBUG: write rendered PNG to file
             // fs::write(
@@ -291,3 +1391,771 @@ fn worker_u32(
 
     Ok(())
 }
+
+/// Floating point types that can be read from a raster band
+trait FloatValue: Copy + PartialEq + Into<f64> + GdalType + std::fmt::Debug {
+    fn from_f64(value: f64) -> Self;
+}
+
+impl FloatValue for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl FloatValue for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+fn worker_float<T: FloatValue>(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    format: TileFormat,
+    stretch: LinearStretch,
+    ramp: &Option<ColorRamp>,
+    ramp_palette: bool,
+    opacity: u8,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+    // NaN is always treated as NODATA
+    let nodata = T::from_f64(band.no_data_value().unwrap_or(f64::NAN));
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    // stretched values are 1-255; 0 is reserved for NODATA
+    let grayscale_encoder: Box<dyn Encode<u8>> =
+        grayscale_encoder(format, width, height, 0u8, None, opacity);
+
+    // NODATA is encoded as black in RGB output; colors from the ramp that
+    // match are shifted slightly so they are not rendered as transparent
+    let nodata_color = Rgb8 { r: 0, g: 0, b: 0 };
+    let rgb_encoder: Box<dyn Encode<u8>> = rgb_encoder(format, width, height, 0u32, opacity);
+
+    let palette_encoder = match ramp {
+        Some(r) if ramp_palette => {
+            let mut colormap = r.palette();
+            colormap.apply_opacity(opacity);
+            Some(colormap_encoder(format, width, height, colormap))
+        }
+        _ => None,
+    };
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut buffer = vec![nodata; buffer_size];
+    let mut gray_buffer: Vec<u8> = vec![0u8; buffer_size];
+    let mut rgb_buffer: Vec<u8> = vec![0u8; buffer_size * 3];
+    let mut color: Rgb8;
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            match (&palette_encoder, ramp) {
+                (Some(encoder), _) => {
+                    stretch.stretch(&buffer, nodata, &mut gray_buffer);
+                    png_data = encoder.encode(&gray_buffer)?;
+                }
+                (None, Some(r)) => {
+                    for (i, &value) in buffer.iter().enumerate() {
+                        color = if is_nodata(value, nodata) {
+                            nodata_color
+                        } else {
+                            match r.color(value.into()) {
+                                c if c == nodata_color => Rgb8 { r: 0, g: 0, b: 1 },
+                                c => c,
+                            }
+                        };
+                        rgb_buffer[i * 3] = color.r;
+                        rgb_buffer[i * 3 + 1] = color.g;
+                        rgb_buffer[i * 3 + 2] = color.b;
+                    }
+                    png_data = rgb_encoder.encode_8bit(&rgb_buffer)?;
+                }
+                _ => {
+                    stretch.stretch(&buffer, nodata, &mut gray_buffer);
+                    png_data = grayscale_encoder.encode_8bit(&gray_buffer)?;
+                }
+            }
+
+            db.write_tile(&tile_id, &png_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn worker_classes(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    format: TileFormat,
+    classes: &ClassifiedColormap,
+    opacity: u8,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+    // NaN is always treated as NODATA
+    let nodata = band.no_data_value().unwrap_or(f64::NAN);
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    // values are classified to class indexes 1..n; 0 is reserved for NODATA
+    let mut colormap = classes.colormap();
+    colormap.apply_opacity(opacity);
+    let encoder: Box<dyn Encode<u8>> = colormap_encoder(format, width, height, colormap);
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut buffer = vec![nodata; buffer_size];
+    let mut class_buffer: Vec<u8> = vec![0u8; buffer_size];
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            for (i, &value) in buffer.iter().enumerate() {
+                class_buffer[i] = if is_nodata(value, nodata) {
+                    0u8
+                } else {
+                    classes.classify(value)
+                };
+            }
+
+            png_data = encoder.encode(&class_buffer)?;
+            db.write_tile(&tile_id, &png_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn worker_elevation(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    format: TileFormat,
+    elevation: ElevationEncoding,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+    // NaN is always treated as NODATA
+    let nodata = band.no_data_value().unwrap_or(f64::NAN);
+
+    let width: u32 = tilesize as u32;
+    let height: u32 = width;
+
+    // NODATA is encoded as the lowest value (0,0,0), which is never used for
+    // valid elevation values
+    let rgb_encoder: Box<dyn Encode<u8>> = rgb_encoder(format, width, height, 0u32, 255u8);
+
+    let buffer_size = tilesize as usize * tilesize as usize;
+    let mut buffer = vec![nodata; buffer_size];
+    let mut rgb_buffer: Vec<u8> = vec![0u8; buffer_size * 3];
+    let mut color: Rgb8;
+    let mut png_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            for (i, &value) in buffer.iter().enumerate() {
+                color = if is_nodata(value, nodata) {
+                    Rgb8 { r: 0, g: 0, b: 0 }
+                } else {
+                    elevation.encode(value)
+                };
+                rgb_buffer[i * 3] = color.r;
+                rgb_buffer[i * 3 + 1] = color.g;
+                rgb_buffer[i * 3 + 2] = color.b;
+            }
+
+            png_data = rgb_encoder.encode_8bit(&rgb_buffer)?;
+            db.write_tile(&tile_id, &png_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn worker_data<T: DataValue + PartialEq + GdalType + std::fmt::Debug>(
+    tiles: channel::Receiver<TileID>,
+    tiff_filename: &PathBuf,
+    tms: &TileMatrixSet,
+    clip: &[Bounds],
+    cutline: Option<&Cutline>,
+    db: &dyn TileWriter,
+    tilesize: u16,
+    nodata: f64,
+    disable_overviews: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(tiff_filename, disable_overviews)?;
+    let vrt = dataset.tms_vrt(tms, clip, cutline)?;
+    let band = vrt.band(1)?;
+    let nodata = T::from_f64(nodata);
+
+    let encoder = DataEncoder::new();
+
+    let mut buffer = vec![nodata; tilesize as usize * tilesize as usize];
+    let mut tile_data: Vec<u8>;
+
+    for tile_id in tiles.iter() {
+        if vrt.read_tile(
+            &band,
+            &tms.tile_bounds(&tile_id),
+            tilesize,
+            &mut buffer,
+            nodata,
+        )? {
+            tile_data = encoder.encode(&buffer)?;
+            db.write_tile(&tile_id, &tile_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::affine::Affine;
+    use crate::test::{write_fixture, write_palette_fixture};
+    use crate::tileid::ORIGIN;
+    use rstest::rstest;
+    use std::sync::Mutex;
+
+    /// Tile writer that keeps tiles in memory
+    #[derive(Default)]
+    struct MemoryWriter {
+        tiles: Mutex<Vec<(TileID, Vec<u8>)>>,
+    }
+
+    impl TileWriter for MemoryWriter {
+        fn set_metadata(&self, _metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn write_tile(&self, tile_id: &TileID, tile_data: &[u8]) -> Result<()> {
+            self.tiles
+                .lock()
+                .unwrap()
+                .push((*tile_id, tile_data.to_vec()));
+            Ok(())
+        }
+
+        fn finish(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Render tiles using worker, ordered by column and row
+    fn render<F>(tiles: &[TileID], worker: F) -> Vec<(TileID, Vec<u8>)>
+    where
+        F: FnOnce(channel::Receiver<TileID>, &dyn TileWriter) -> Result<(), Box<dyn Error>>,
+    {
+        let (snd, rcv) = channel::unbounded();
+        for tile_id in tiles {
+            snd.send(*tile_id).unwrap();
+        }
+        drop(snd);
+
+        let writer = MemoryWriter::default();
+        worker(rcv, &writer).unwrap();
+
+        let mut tiles = writer.tiles.into_inner().unwrap();
+        tiles.sort_by_key(|(tile_id, _)| (tile_id.x, tile_id.y));
+        tiles
+    }
+
+    /// Decode PNG to RGBA pixels
+    fn decode_rgba(png_data: &[u8]) -> Vec<u8> {
+        let mut decoder = ::png::Decoder::new(png_data);
+        decoder.set_transformations(::png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        match reader.output_color_type().0 {
+            ::png::ColorType::Rgb => pixels
+                .chunks(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            _ => pixels,
+        }
+    }
+
+    #[test]
+    fn worker_float_integer_ramp() {
+        // uint8 data covering the northwest quadrant of the world
+        let transform = Affine::new(ORIGIN / 100., 0., -ORIGIN, 0., -ORIGIN / 100., ORIGIN);
+        let path = write_fixture(
+            "rastertiler_integer_ramp.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            1,
+            100u8,
+            None,
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let ramp = ColorRamp::parse("0:#000000,200:#FF0000").unwrap();
+        let stretch = LinearStretch::new(ramp.min(), ramp.max());
+        let tiles = render(&[TileID::new(1, 0, 0)], |rcv, db| {
+            worker_float::<f64>(
+                rcv,
+                &path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                stretch,
+                &Some(ramp),
+                false,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tiles.len(), 1);
+        let pixels = decode_rgba(&tiles[0].1);
+        assert_eq!(pixels[..4], [128, 0, 0, 255]);
+        assert_eq!(pixels[pixels.len() - 4..], [128, 0, 0, 255]);
+    }
+
+    #[test]
+    fn worker_16bit_without_nodata() {
+        // data without NODATA covering the northwest quadrant of the world
+        let transform = Affine::new(ORIGIN / 100., 0., -ORIGIN, 0., -ORIGIN / 100., ORIGIN);
+        let u16_path = write_fixture(
+            "rastertiler_u16_without_nodata.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            1,
+            1000u16,
+            None,
+        );
+        let i16_path = write_fixture(
+            "rastertiler_i16_without_nodata.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            1,
+            -1000i16,
+            None,
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let stretch = Some(LinearStretch::new(-2000., 2000.));
+        let u16_tiles = render(&[TileID::new(0, 0, 0)], |rcv, db| {
+            worker_u16(
+                rcv,
+                &u16_path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                &None,
+                stretch,
+                255,
+                false,
+            )
+        });
+        let i16_tiles = render(&[TileID::new(0, 0, 0)], |rcv, db| {
+            worker_i16(
+                rcv,
+                &i16_path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                &None,
+                stretch,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&u16_path).unwrap();
+        std::fs::remove_file(&i16_path).unwrap();
+
+        // grayscale pixels are expanded to gray and alpha; pixels outside the
+        // dataset are transparent
+        for tiles in [u16_tiles, i16_tiles] {
+            assert_eq!(tiles.len(), 1);
+            let pixels = decode_rgba(&tiles[0].1);
+            assert_eq!(pixels[1], 255);
+            assert_eq!(pixels[pixels.len() - 1], 0);
+        }
+    }
+
+    #[test]
+    fn worker_gray16_without_nodata() {
+        // uint16 data without NODATA covering the northwest quadrant of the world
+        let transform = Affine::new(ORIGIN / 100., 0., -ORIGIN, 0., -ORIGIN / 100., ORIGIN);
+        let path = write_fixture(
+            "rastertiler_gray16_without_nodata.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            1,
+            1000u16,
+            None,
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let tiles = render(&[TileID::new(0, 0, 0)], |rcv, db| {
+            worker_gray16(rcv, &path, &tms, &[], None, db, 64, 0, false)
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tiles.len(), 1);
+        let mut decoder = ::png::Decoder::new(&tiles[0].1[..]);
+        decoder.set_transformations(::png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        // values are stored in big-endian order; pixels outside the dataset
+        // are the NODATA value of the metadata
+        assert_eq!(reader.info().trns.as_deref(), Some([0u8, 0].as_slice()));
+        assert_eq!(pixels[..2], [3, 232]);
+        assert_eq!(pixels[pixels.len() - 2..], [0, 0]);
+    }
+
+    #[test]
+    fn worker_rgb_without_alpha_band() {
+        // RGBN data covering the northwest quadrant of the world; band 4 is
+        // near-infrared rather than alpha
+        let transform = Affine::new(ORIGIN / 100., 0., -ORIGIN, 0., -ORIGIN / 100., ORIGIN);
+        let path = write_fixture(
+            "rastertiler_rgbn.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            4,
+            100u8,
+            None,
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let tiles = render(&[TileID::new(0, 0, 0)], |rcv, db| {
+            worker_rgb(
+                rcv,
+                &path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tiles.len(), 1);
+        let pixels = decode_rgba(&tiles[0].1);
+        assert_eq!(pixels[..4], [100, 100, 100, 255]);
+        assert_eq!(pixels[pixels.len() - 4..], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn worker_u8_palette_without_nodata() {
+        // paletted data without NODATA covering the northwest quadrant of the
+        // world; index 0 is used by the color table
+        let transform = Affine::new(ORIGIN / 100., 0., -ORIGIN, 0., -ORIGIN / 100., ORIGIN);
+        let path = write_palette_fixture(
+            "rastertiler_palette_without_nodata.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            0,
+            &[(255, 0, 0, 255), (0, 255, 0, 255)],
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let tiles = render(&[TileID::new(0, 0, 0)], |rcv, db| {
+            worker_u8(
+                rcv,
+                &path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                &None,
+                true,
+                None,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        // pixels outside the dataset are transparent rather than index 0
+        assert_eq!(tiles.len(), 1);
+        let pixels = decode_rgba(&tiles[0].1);
+        assert_eq!(pixels[..4], [255, 0, 0, 255]);
+        assert_eq!(pixels[pixels.len() - 4..], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn worker_u8_antimeridian() {
+        // uint8 data from longitude 170 to 190, crossing the antimeridian
+        let transform = Affine::new(1., 0., 170., 0., -1., 10.);
+        let path = write_fixture(
+            "rastertiler_antimeridian.tif",
+            (20, 20),
+            &transform,
+            "EPSG:4326",
+            1,
+            100u8,
+            Some(0.),
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let tiles = render(&[TileID::new(1, 0, 0), TileID::new(1, 1, 0)], |rcv, db| {
+            worker_u8(
+                rcv,
+                &path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                &None,
+                false,
+                None,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        // tiles on both sides of the antimeridian have data next to it in
+        // the bottom row, which is just north of the equator
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[0].0, TileID::new(1, 0, 0));
+        assert_eq!(tiles[1].0, TileID::new(1, 1, 0));
+
+        // grayscale pixels are expanded to gray and alpha
+        let west = decode_rgba(&tiles[0].1);
+        assert_eq!(west[63 * 64 * 2..63 * 64 * 2 + 2], [100, 255]);
+        assert_eq!(west[west.len() - 2..], [0, 0]);
+
+        let east = decode_rgba(&tiles[1].1);
+        assert_eq!(east[63 * 64 * 2..63 * 64 * 2 + 2], [0, 0]);
+        assert_eq!(east[east.len() - 2..], [100, 255]);
+    }
+
+    #[test]
+    fn worker_u8_antimeridian_clip() {
+        // uint8 data from longitude 170 to 190, clipped to 175 to 185
+        let transform = Affine::new(1., 0., 170., 0., -1., 10.);
+        let path = write_fixture(
+            "rastertiler_antimeridian_clip.tif",
+            (20, 20),
+            &transform,
+            "EPSG:4326",
+            1,
+            100u8,
+            Some(0.),
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let clip: Vec<Bounds> = Bounds::parse("175,-10,185,10")
+            .unwrap()
+            .split_antimeridian()
+            .iter()
+            .map(|c| geo_to_tms_bounds(c, &tms).unwrap())
+            .collect();
+        let tiles = render(&[TileID::new(1, 0, 0), TileID::new(1, 1, 0)], |rcv, db| {
+            worker_u8(
+                rcv,
+                &path,
+                &tms,
+                &clip,
+                None,
+                db,
+                64,
+                TileFormat::Png,
+                &None,
+                false,
+                None,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        // grayscale pixels are expanded to gray and alpha; columns are 2.8125
+        // degrees wide, and the bottom row is just north of the equator
+        assert_eq!(tiles.len(), 2);
+        let pixel = |data: &[u8], col: usize| {
+            let pixels = decode_rgba(data);
+            pixels[(63 * 64 + col) * 2..(63 * 64 + col) * 2 + 2].to_vec()
+        };
+
+        // west of the antimeridian, -180 to -177.1875 is within the clip
+        assert_eq!(tiles[0].0, TileID::new(1, 0, 0));
+        assert_eq!(pixel(&tiles[0].1, 0), [100, 255]);
+        assert_eq!(pixel(&tiles[0].1, 2), [0, 0]);
+
+        // east of the antimeridian, 171.5625 to 174.375 is outside the clip
+        assert_eq!(tiles[1].0, TileID::new(1, 1, 0));
+        assert_eq!(pixel(&tiles[1].1, 61), [0, 0]);
+        assert_eq!(pixel(&tiles[1].1, 63), [100, 255]);
+    }
+
+    #[test]
+    fn worker_u32_mixed_palette() {
+        // blue packed RGB data covering the northwest quadrant of the world
+        let transform = Affine::new(ORIGIN / 100., 0., -ORIGIN, 0., -ORIGIN / 100., ORIGIN);
+        let path = write_fixture(
+            "rastertiler_mixed_palette.tif",
+            (100, 100),
+            &transform,
+            "EPSG:3857",
+            1,
+            0x0000FFu32,
+            Some(0.),
+        );
+
+        let tms = TileMatrixSet::web_mercator_quad();
+        let tiles = render(&[TileID::new(0, 0, 0), TileID::new(1, 0, 0)], |rcv, db| {
+            worker_u32(
+                rcv,
+                &path,
+                &tms,
+                &[],
+                None,
+                db,
+                64,
+                TileFormat::Mixed { quality: 75 },
+                &None,
+                None,
+                255,
+                false,
+            )
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tiles.len(), 2);
+
+        // partially covered tile falls back to a paletted PNG
+        let (tile_id, data) = &tiles[0];
+        assert_eq!(*tile_id, TileID::new(0, 0, 0));
+        let reader = ::png::Decoder::new(&data[..]).read_info().unwrap();
+        assert_eq!(reader.info().color_type, ::png::ColorType::Indexed);
+
+        // fully covered tile is JPEG
+        let (tile_id, data) = &tiles[1];
+        assert_eq!(*tile_id, TileID::new(1, 0, 0));
+        assert_eq!(data[..2], [0xFF, 0xD8]);
+    }
+
+    #[rstest]
+    #[case("1:#686868,255:#fbb4b9", GdalDataType::UInt8, true)]
+    #[case("256:#686868", GdalDataType::UInt8, false)]
+    #[case("256:#686868", GdalDataType::UInt16, true)]
+    #[case("-1:#686868", GdalDataType::UInt8, false)]
+    #[case("-1:#686868", GdalDataType::UInt32, false)]
+    #[case("-1:#686868", GdalDataType::Int16, true)]
+    #[case("1.5:#686868", GdalDataType::UInt16, false)]
+    #[case("1:#686868", GdalDataType::Float32, false)]
+    fn check_colormap_values(
+        #[case] colormap: &str,
+        #[case] dtype: GdalDataType,
+        #[case] valid: bool,
+    ) {
+        let entries = parse_colormap(colormap).unwrap();
+        assert_eq!(check_colormap(&entries, dtype).is_ok(), valid);
+    }
+
+    #[rstest]
+    #[case("txt", "1 104 104 104\n256 251 180 185")]
+    #[case("clr", "1,104,104,104,255,low\n256,251,180,185,255,high")]
+    #[case(
+        "json",
+        r##"[{"value": 1, "color": "#686868"}, {"value": 256, "color": "#fbb4b9"}]"##
+    )]
+    fn check_colormap_file(#[case] extension: &str, #[case] contents: &str) {
+        let path = std::env::temp_dir().join(format!("rastertiler_check_colormap.{}", extension));
+        std::fs::write(&path, contents).unwrap();
+        let entries = read_colormap_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // 256 is out of range for uint8
+        assert!(check_colormap(&entries, GdalDataType::UInt8).is_err());
+        assert!(check_colormap(&entries, GdalDataType::UInt16).is_ok());
+    }
+
+    #[rstest]
+    #[case("example.mbtiles", "example")]
+    #[case("tiles/", "tiles")]
+    #[case("./", "input")]
+    #[case("/", "input")]
+    fn default_name(#[case] output: &str, #[case] expected: &str) {
+        assert_eq!(
+            super::default_name(Path::new(output), Path::new("/data/input.tif")),
+            expected
+        );
+    }
+
+    #[test]
+    fn check_colormap_too_many_entries() {
+        let colormap = (0..256)
+            .map(|i| format!("{}:#686868", i))
+            .collect::<Vec<_>>()
+            .join(",");
+        let entries = parse_colormap(&colormap).unwrap();
+        assert!(check_colormap(&entries, GdalDataType::UInt16).is_err());
+    }
+}
@@ -6,8 +6,8 @@ use std::f64::consts::PI;
 use crate::bounds::Bounds;
 
 const RE: f64 = 6378137.0;
-const ORIGIN: f64 = RE * PI;
-const CE: f64 = 2.0 * ORIGIN;
+pub const ORIGIN: f64 = RE * PI;
+pub const CE: f64 = 2.0 * ORIGIN;
 
 /// Calculate Mercator coordinates for geographic coordinates.
 /// Coordinates are clipped to -180 to 180 and -85.051129 to 85.051129.
@@ -80,6 +80,36 @@ impl TileID {
             ymax,
         }
     }
+
+    /// Calculate the PMTiles tile ID, which orders tiles by zoom level and
+    /// then along a Hilbert curve within each zoom level
+    pub fn hilbert_id(&self) -> u64 {
+        // number of tiles in all lower zoom levels
+        let acc = ((1u64 << (2 * self.zoom as u64)) - 1) / 3;
+
+        let n = 1u64 << self.zoom;
+        let mut x = self.x as u64;
+        let mut y = self.y as u64;
+        let mut d = 0u64;
+        let mut s = n / 2;
+        while s > 0 {
+            let rx = ((x & s) > 0) as u64;
+            let ry = ((y & s) > 0) as u64;
+            d += s * s * ((3 * rx) ^ ry);
+
+            // rotate quadrant
+            if ry == 0 {
+                if rx == 1 {
+                    x = n - 1 - x;
+                    y = n - 1 - y;
+                }
+                std::mem::swap(&mut x, &mut y);
+            }
+            s /= 2;
+        }
+
+        acc + d
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -135,6 +165,14 @@ impl TileRange {
             * (self.ymax as usize - self.ymin as usize + 1)
     }
 
+    pub fn contains(&self, tile_id: &TileID) -> bool {
+        tile_id.zoom == self.zoom
+            && tile_id.x >= self.xmin
+            && tile_id.x <= self.xmax
+            && tile_id.y >= self.ymin
+            && tile_id.y <= self.ymax
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = TileID> {
         let zoom = self.zoom;
 
@@ -196,6 +234,18 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(TileID{zoom: 0, x: 0, y: 0}, 0)]
+    #[case(TileID{zoom: 1, x: 0, y: 0}, 1)]
+    #[case(TileID{zoom: 1, x: 0, y: 1}, 2)]
+    #[case(TileID{zoom: 1, x: 1, y: 1}, 3)]
+    #[case(TileID{zoom: 1, x: 1, y: 0}, 4)]
+    #[case(TileID{zoom: 2, x: 0, y: 0}, 5)]
+    #[case(TileID{zoom: 12, x: 3423, y: 1763}, 19078479)]
+    fn hilbert_id(#[case] tile: TileID, #[case] expected: u64) {
+        assert_eq!(tile.hilbert_id(), expected);
+    }
+
     #[rstest]
     #[case(TileID{zoom: 0, x: 0, y: 0}, Bounds{xmin: -20037508.342789, ymin: -20037508.342789, xmax: 20037508.342789, ymax: 20037508.342789})]
     #[case(TileID{zoom: 1, x: 1, y: 1}, Bounds{xmin: 0.0, ymin: -20037508.342789, xmax: 20037508.342789, ymax: 0.0})]
@@ -278,4 +328,21 @@ mod tests {
         assert_eq!(*actual.first().unwrap(), first);
         assert_eq!(*actual.last().unwrap(), last);
     }
+
+    #[rstest]
+    #[case(TileID{zoom: 4, x: 3, y: 7}, true)]
+    #[case(TileID{zoom: 4, x: 7, y: 8}, true)]
+    #[case(TileID{zoom: 4, x: 8, y: 8}, false)]
+    #[case(TileID{zoom: 4, x: 3, y: 6}, false)]
+    #[case(TileID{zoom: 5, x: 3, y: 7}, false)]
+    fn tile_range_contains(#[case] tile: TileID, #[case] expected: bool) {
+        let range = TileRange {
+            zoom: 4,
+            xmin: 3,
+            ymin: 7,
+            xmax: 7,
+            ymax: 8,
+        };
+        assert_eq!(range.contains(&tile), expected);
+    }
 }
@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::bounds::Bounds;
+
+/// TileJSON 3.0 document describing a rendered tileset
+pub struct TileJSON<'a> {
+    /// URL template of tiles, e.g., "https://example.com/{z}/{x}/{y}.png"
+    pub tile_url: &'a str,
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub attribution: Option<&'a str>,
+    pub minzoom: u8,
+    pub maxzoom: u8,
+    /// Bounds in geographic coordinates; xmin is greater than xmax if bounds
+    /// cross the antimeridian, in which case they are written as
+    /// "antimeridian_adjusted_bounds" and "bounds" has the full range of
+    /// longitudes
+    pub bounds: &'a Bounds,
+    /// Tile row order, "xyz" or "tms"
+    pub scheme: &'a str,
+}
+
+impl TileJSON<'_> {
+    pub fn to_json(&self) -> Value {
+        let (center_x, center_y) = self.bounds.center();
+        let bounds = self.bounds.expand_antimeridian();
+        let mut tilejson = json!({
+            "tilejson": "3.0.0",
+            "tiles": [self.tile_url],
+            "name": self.name,
+            "scheme": self.scheme,
+            "minzoom": self.minzoom,
+            "maxzoom": self.maxzoom,
+            "bounds": [bounds.xmin, bounds.ymin, bounds.xmax, bounds.ymax],
+            "center": [center_x, center_y, self.minzoom],
+        });
+
+        if self.bounds.xmin > self.bounds.xmax {
+            tilejson["antimeridian_adjusted_bounds"] = json!([
+                self.bounds.xmin,
+                self.bounds.ymin,
+                self.bounds.xmax,
+                self.bounds.ymax
+            ]);
+        }
+
+        if let Some(description) = self.description {
+            tilejson["description"] = Value::from(description);
+        }
+
+        if let Some(attribution) = self.attribution {
+            tilejson["attribution"] = Value::from(attribution);
+        }
+
+        tilejson
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.to_json())?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_json() {
+        let bounds = Bounds {
+            xmin: -10.,
+            ymin: -5.,
+            xmax: 10.,
+            ymax: 15.,
+        };
+        let tilejson = TileJSON {
+            tile_url: "https://example.com/{z}/{x}/{y}.png",
+            name: "example",
+            description: None,
+            attribution: Some("Example"),
+            minzoom: 2,
+            maxzoom: 4,
+            bounds: &bounds,
+            scheme: "xyz",
+        };
+
+        assert_eq!(
+            tilejson.to_json(),
+            json!({
+                "tilejson": "3.0.0",
+                "tiles": ["https://example.com/{z}/{x}/{y}.png"],
+                "name": "example",
+                "attribution": "Example",
+                "scheme": "xyz",
+                "minzoom": 2,
+                "maxzoom": 4,
+                "bounds": [-10., -5., 10., 15.],
+                "center": [0., 5., 2],
+            })
+        );
+    }
+
+    #[test]
+    fn to_json_antimeridian() {
+        let bounds = Bounds {
+            xmin: 170.,
+            ymin: -5.,
+            xmax: -160.,
+            ymax: 15.,
+        };
+        let tilejson = TileJSON {
+            tile_url: "https://example.com/{z}/{x}/{y}.png",
+            name: "example",
+            description: None,
+            attribution: None,
+            minzoom: 2,
+            maxzoom: 4,
+            bounds: &bounds,
+            scheme: "xyz",
+        }
+        .to_json();
+
+        assert_eq!(tilejson["bounds"], json!([-180., -5., 180., 15.]));
+        assert_eq!(
+            tilejson["antimeridian_adjusted_bounds"],
+            json!([170., -5., -160., 15.])
+        );
+        assert_eq!(tilejson["center"], json!([-175., 5., 2]));
+    }
+}
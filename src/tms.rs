@@ -0,0 +1,508 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::bounds::Bounds;
+use crate::tileid::{TileID, TileRange, CE, ORIGIN};
+
+/// Number of zoom levels of built-in tile matrix sets
+const BUILTIN_ZOOM_LEVELS: u8 = 25;
+
+/// Tile size (in pixels) of built-in tile matrix sets
+const BUILTIN_TILE_SIZE: u32 = 256;
+
+/// Zoom level provided by the user, or "auto" to calculate it from the
+/// resolution of the dataset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoomLevel {
+    Auto,
+    Level(u8),
+}
+
+/// Tile matrix of a single zoom level
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMatrix {
+    /// Size of a pixel in CRS units, at the tile size of the matrix
+    pub cell_size: f64,
+    /// Coordinates (x, y) of the top left corner of the matrix
+    pub origin: (f64, f64),
+    /// Tile width in pixels
+    pub tile_width: u32,
+    /// Tile height in pixels
+    pub tile_height: u32,
+    /// Number of tiles in each row
+    pub matrix_width: u32,
+    /// Number of tiles in each column
+    pub matrix_height: u32,
+}
+
+impl TileMatrix {
+    /// Width of a tile in CRS units
+    fn tile_span_x(&self) -> f64 {
+        self.cell_size * self.tile_width as f64
+    }
+
+    /// Height of a tile in CRS units
+    fn tile_span_y(&self) -> f64 {
+        self.cell_size * self.tile_height as f64
+    }
+}
+
+/// OGC TileMatrixSet that defines the CRS and grid of tiles at each zoom level;
+/// zoom levels are the index of each tile matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMatrixSet {
+    pub id: String,
+    /// CRS definition, e.g., "EPSG:3857"
+    pub crs: String,
+    pub matrices: Vec<TileMatrix>,
+}
+
+impl TileMatrixSet {
+    /// Built-in WebMercatorQuad tile matrix set (EPSG:3857)
+    pub fn web_mercator_quad() -> TileMatrixSet {
+        TileMatrixSet {
+            id: String::from("WebMercatorQuad"),
+            crs: String::from("EPSG:3857"),
+            matrices: (0..BUILTIN_ZOOM_LEVELS)
+                .map(|zoom| TileMatrix {
+                    cell_size: CE / (BUILTIN_TILE_SIZE as f64 * (1u64 << zoom) as f64),
+                    origin: (-ORIGIN, ORIGIN),
+                    tile_width: BUILTIN_TILE_SIZE,
+                    tile_height: BUILTIN_TILE_SIZE,
+                    matrix_width: 1 << zoom,
+                    matrix_height: 1 << zoom,
+                })
+                .collect(),
+        }
+    }
+
+    /// Built-in WorldCRS84Quad tile matrix set (OGC:CRS84), which has 2 tiles
+    /// at zoom level 0
+    pub fn world_crs84_quad() -> TileMatrixSet {
+        TileMatrixSet {
+            id: String::from("WorldCRS84Quad"),
+            crs: String::from("OGC:CRS84"),
+            matrices: (0..BUILTIN_ZOOM_LEVELS)
+                .map(|zoom| TileMatrix {
+                    cell_size: 180. / (BUILTIN_TILE_SIZE as f64 * (1u64 << zoom) as f64),
+                    origin: (-180., 90.),
+                    tile_width: BUILTIN_TILE_SIZE,
+                    tile_height: BUILTIN_TILE_SIZE,
+                    matrix_width: 2 << zoom,
+                    matrix_height: 1 << zoom,
+                })
+                .collect(),
+        }
+    }
+
+    /// Get built-in tile matrix set by id, or read it from a JSON file
+    pub fn from_name_or_file(value: &str) -> Result<TileMatrixSet, Box<dyn Error>> {
+        match value {
+            "WebMercatorQuad" => Ok(TileMatrixSet::web_mercator_quad()),
+            "WorldCRS84Quad" => Ok(TileMatrixSet::world_crs84_quad()),
+            _ => TileMatrixSet::read_json(Path::new(value)),
+        }
+    }
+
+    /// Read tile matrix set from a JSON file following the OGC Two Dimensional
+    /// Tile Matrix Set and Tile Set Metadata 2.0 specification
+    pub fn read_json(path: &Path) -> Result<TileMatrixSet, Box<dyn Error>> {
+        TileMatrixSet::parse_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parse tile matrix set from JSON.  Only tile matrices with a top left
+    /// corner of origin and square tiles are supported; points of origin are
+    /// expected in the axis order listed in "orderedAxes", if provided.
+    pub fn parse_json(contents: &str) -> Result<TileMatrixSet, Box<dyn Error>> {
+        let json: Value = serde_json::from_str(contents)?;
+
+        let id = json["id"].as_str().unwrap_or("custom").to_owned();
+
+        let crs = match &json["crs"] {
+            Value::String(uri) => crs_from_uri(uri),
+            Value::Object(crs) => match crs.get("uri").and_then(|uri| uri.as_str()) {
+                Some(uri) => crs_from_uri(uri),
+                None => return Err("tile matrix set crs must be provided as a URI".into()),
+            },
+            _ => return Err("tile matrix set must have a crs".into()),
+        };
+
+        // northing / latitude first
+        let swap_axes = match json["orderedAxes"].as_array().and_then(|a| a.first()) {
+            Some(axis) => matches!(
+                axis.as_str().unwrap_or("").to_lowercase().as_str(),
+                "lat" | "latitude" | "y" | "n" | "northing"
+            ),
+            None => false,
+        };
+
+        let Some(items) = json["tileMatrices"].as_array() else {
+            return Err("tile matrix set must have tileMatrices".into());
+        };
+
+        let mut matrices: Vec<TileMatrix> = Vec::with_capacity(items.len());
+        for item in items {
+            if let Some(corner) = item["cornerOfOrigin"].as_str()
+                && corner != "topLeft"
+            {
+                return Err(format!("unsupported cornerOfOrigin: {}", corner).into());
+            }
+
+            let origin = match item["pointOfOrigin"].as_array().map(|p| p.as_slice()) {
+                Some([a, b]) => match (a.as_f64(), b.as_f64()) {
+                    (Some(a), Some(b)) if swap_axes => (b, a),
+                    (Some(a), Some(b)) => (a, b),
+                    _ => return Err(format!("invalid pointOfOrigin: {}", item).into()),
+                },
+                _ => return Err(format!("tile matrix must have pointOfOrigin: {}", item).into()),
+            };
+
+            let number = |key: &str| {
+                item[key]
+                    .as_u64()
+                    .filter(|&v| v > 0 && v <= u32::MAX as u64)
+                    .map(|v| v as u32)
+                    .ok_or_else(|| format!("tile matrix must have {}: {}", key, item))
+            };
+
+            // tiles are rendered at the same width and height
+            let tile_width = number("tileWidth")?;
+            let tile_height = number("tileHeight")?;
+            if tile_width != tile_height {
+                return Err(format!("tile matrix must have square tiles: {}", item).into());
+            }
+
+            matrices.push(TileMatrix {
+                cell_size: item["cellSize"]
+                    .as_f64()
+                    .filter(|&v| v > 0.)
+                    .ok_or_else(|| format!("tile matrix must have cellSize: {}", item))?,
+                origin,
+                tile_width,
+                tile_height,
+                matrix_width: number("matrixWidth")?,
+                matrix_height: number("matrixHeight")?,
+            });
+        }
+
+        if matrices.is_empty() {
+            return Err("tile matrix set does not contain any tile matrices".into());
+        }
+
+        Ok(TileMatrixSet { id, crs, matrices })
+    }
+
+    /// Check if tile matrix set has the CRS and grid of tiles of
+    /// WebMercatorQuad, regardless of its id or tile size in pixels; it may
+    /// have fewer zoom levels
+    pub fn is_web_mercator(&self) -> bool {
+        let approx_eq = |a: f64, b: f64| (a - b).abs() <= b.abs().max(1.) * 1e-9;
+
+        self.crs == "EPSG:3857"
+            && self.matrices.len() <= BUILTIN_ZOOM_LEVELS as usize
+            && self
+                .matrices
+                .iter()
+                .zip(TileMatrixSet::web_mercator_quad().matrices.iter())
+                .all(|(m, expected)| {
+                    approx_eq(m.tile_span_x(), expected.tile_span_x())
+                        && approx_eq(m.tile_span_y(), expected.tile_span_y())
+                        && approx_eq(m.origin.0, expected.origin.0)
+                        && approx_eq(m.origin.1, expected.origin.1)
+                        && m.matrix_width == expected.matrix_width
+                        && m.matrix_height == expected.matrix_height
+                })
+    }
+
+    /// Width of the world in units of the CRS, for tile matrix sets in Web
+    /// Mercator or geographic coordinates; None for other CRS, which do not
+    /// wrap around the antimeridian
+    pub fn world_width(&self) -> Option<f64> {
+        if self.is_web_mercator() {
+            return Some(CE);
+        }
+        match self.crs.as_str() {
+            "OGC:CRS84" | "EPSG:4326" => Some(360.),
+            _ => None,
+        }
+    }
+
+    /// Maximum zoom level available in tile matrix set
+    pub fn maxzoom(&self) -> u8 {
+        (self.matrices.len() - 1).min(u8::MAX as usize) as u8
+    }
+
+    /// Find the zoom level with a pixel size closest to resolution (in CRS
+    /// units) when tiles are rendered at tilesize; pixel sizes are compared on
+    /// a log scale, so that the finer zoom level is used when the resolution
+    /// is halfway between 2 zoom levels of a quadtree
+    pub fn zoom_for_resolution(&self, resolution: f64, tilesize: u16) -> u8 {
+        let distance = |matrix: &TileMatrix| {
+            let pixel_size = matrix.cell_size * matrix.tile_width as f64 / tilesize as f64;
+            (pixel_size / resolution).ln().abs()
+        };
+
+        let mut zoom = 0;
+        for (i, matrix) in self.matrices.iter().enumerate() {
+            if distance(matrix) <= distance(&self.matrices[zoom]) {
+                zoom = i;
+            }
+        }
+        zoom.min(u8::MAX as usize) as u8
+    }
+
+    /// Calculate bounds of tile in coordinates of the CRS; zoom must be within
+    /// the range of the tile matrix set
+    pub fn tile_bounds(&self, tile_id: &TileID) -> Bounds {
+        let matrix = &self.matrices[tile_id.zoom as usize];
+        let xmin = matrix.origin.0 + tile_id.x as f64 * matrix.tile_span_x();
+        let ymax = matrix.origin.1 - tile_id.y as f64 * matrix.tile_span_y();
+
+        Bounds {
+            xmin,
+            ymin: ymax - matrix.tile_span_y(),
+            xmax: xmin + matrix.tile_span_x(),
+            ymax,
+        }
+    }
+
+    /// Creates a TileRange that covers the bounds at the zoom level
+    ///
+    /// # Arguments
+    /// * `zoom` - zoom level to cover
+    /// * `bounds` - Bounds object containing coordinates of the CRS
+    pub fn tile_range(&self, zoom: u8, bounds: &Bounds) -> TileRange {
+        let matrix = &self.matrices[zoom as usize];
+        let (origin_x, origin_y) = matrix.origin;
+        let span_x = matrix.tile_span_x();
+        let span_y = matrix.tile_span_y();
+        let max_x = matrix.matrix_width as f64 - 1.;
+        let max_y = matrix.matrix_height as f64 - 1.;
+        let eps = 1e-11;
+
+        let col = |x: f64| (x / span_x).floor().clamp(0., max_x) as u32;
+        let row = |y: f64| (y / span_y).floor().clamp(0., max_y) as u32;
+
+        TileRange {
+            zoom,
+            xmin: col(bounds.xmin - origin_x),
+            ymin: row(origin_y - bounds.ymax),
+            xmax: col(bounds.xmax - origin_x - eps * span_x),
+            ymax: row(origin_y - bounds.ymin - eps * span_y),
+        }
+    }
+}
+
+/// Convert OGC CRS URI, e.g., "http://www.opengis.net/def/crs/EPSG/0/3857", to
+/// "<authority>:<code>"; other values are returned unchanged
+fn crs_from_uri(uri: &str) -> String {
+    let parts: Vec<&str> = uri.trim_end_matches('/').split('/').collect();
+    match parts.as_slice() {
+        [.., "def", "crs", authority, _version, code] => format!("{}:{}", authority, code),
+        _ => uri.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::approx_eq_bounds;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(TileID{zoom: 0, x: 0, y: 0})]
+    #[case(TileID{zoom: 1, x: 1, y: 1})]
+    #[case(TileID{zoom: 10, x: 20, y: 30})]
+    fn web_mercator_tile_bounds(#[case] tile: TileID) {
+        let actual = TileMatrixSet::web_mercator_quad().tile_bounds(&tile);
+        let expected = tile.mercator_bounds();
+        assert!(
+            approx_eq_bounds(&actual, &expected, 1e-6),
+            "\n{:?}\n!=\n{:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(0, Bounds{xmin: -ORIGIN, ymin: -ORIGIN, xmax: ORIGIN, ymax: ORIGIN})]
+    #[case(4, Bounds{xmin: -11131949.079327, ymin: -2273030.926988, xmax: -2226389.815865, ymax: 2273030.926988})]
+    #[case(4, Bounds{xmin: -0.1, ymin: -0.1, xmax: 0.1, ymax: 0.1})]
+    fn web_mercator_tile_range(#[case] zoom: u8, #[case] bounds: Bounds) {
+        assert_eq!(
+            TileMatrixSet::web_mercator_quad().tile_range(zoom, &bounds),
+            TileRange::new(zoom, &bounds)
+        );
+    }
+
+    #[rstest]
+    #[case(TileID{zoom: 0, x: 0, y: 0}, Bounds{xmin: -180., ymin: -90., xmax: 0., ymax: 90.})]
+    #[case(TileID{zoom: 0, x: 1, y: 0}, Bounds{xmin: 0., ymin: -90., xmax: 180., ymax: 90.})]
+    #[case(TileID{zoom: 2, x: 3, y: 1}, Bounds{xmin: -45., ymin: 0., xmax: 0., ymax: 45.})]
+    fn world_crs84_tile_bounds(#[case] tile: TileID, #[case] expected: Bounds) {
+        let actual = TileMatrixSet::world_crs84_quad().tile_bounds(&tile);
+        assert!(
+            approx_eq_bounds(&actual, &expected, 1e-9),
+            "\n{:?}\n!=\n{:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(0, Bounds{xmin: -180., ymin: -90., xmax: 180., ymax: 90.}, TileRange{zoom: 0, xmin: 0, ymin: 0, xmax: 1, ymax: 0})]
+    #[case(2, Bounds{xmin: -100., ymin: -20., xmax: -20., ymax: 20.}, TileRange{zoom: 2, xmin: 1, ymin: 1, xmax: 3, ymax: 2})]
+    #[case(2, Bounds{xmin: -200., ymin: -100., xmax: 200., ymax: 100.}, TileRange{zoom: 2, xmin: 0, ymin: 0, xmax: 7, ymax: 3})]
+    fn world_crs84_tile_range(
+        #[case] zoom: u8,
+        #[case] bounds: Bounds,
+        #[case] expected: TileRange,
+    ) {
+        assert_eq!(
+            TileMatrixSet::world_crs84_quad().tile_range(zoom, &bounds),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(TileMatrixSet::web_mercator_quad(), 256, 156543.034, 0)]
+    #[case(TileMatrixSet::web_mercator_quad(), 256, 78271.517, 1)]
+    #[case(TileMatrixSet::web_mercator_quad(), 512, 78271.517, 0)]
+    #[case(TileMatrixSet::web_mercator_quad(), 256, 30., 12)]
+    #[case(TileMatrixSet::web_mercator_quad(), 512, 30., 11)]
+    #[case(TileMatrixSet::web_mercator_quad(), 256, 0.01, 24)]
+    #[case(TileMatrixSet::web_mercator_quad(), 256, 1e9, 0)]
+    #[case(TileMatrixSet::world_crs84_quad(), 256, 0.0025, 8)]
+    fn zoom_for_resolution(
+        #[case] tms: TileMatrixSet,
+        #[case] tilesize: u16,
+        #[case] resolution: f64,
+        #[case] expected: u8,
+    ) {
+        assert_eq!(tms.zoom_for_resolution(resolution, tilesize), expected);
+    }
+
+    #[test]
+    fn parse_json() {
+        let tms = TileMatrixSet::parse_json(
+            r#"{
+                "id": "Polar",
+                "crs": {"uri": "http://www.opengis.net/def/crs/EPSG/0/5041"},
+                "orderedAxes": ["N", "E"],
+                "tileMatrices": [
+                    {
+                        "id": "0",
+                        "cellSize": 100.0,
+                        "cornerOfOrigin": "topLeft",
+                        "pointOfOrigin": [2000.0, -1000.0],
+                        "tileWidth": 256,
+                        "tileHeight": 256,
+                        "matrixWidth": 2,
+                        "matrixHeight": 1
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(tms.id, "Polar");
+        assert_eq!(tms.crs, "EPSG:5041");
+        assert_eq!(tms.maxzoom(), 0);
+        assert_eq!(tms.matrices[0].origin, (-1000., 2000.));
+
+        let bounds = tms.tile_bounds(&TileID::new(0, 1, 0));
+        assert!(approx_eq_bounds(
+            &bounds,
+            &Bounds {
+                xmin: 24600.,
+                ymin: -23600.,
+                xmax: 50200.,
+                ymax: 2000.
+            },
+            1e-9
+        ));
+    }
+
+    #[rstest]
+    #[case(r#"{"tileMatrices": []}"#)]
+    #[case(r#"{"crs": "EPSG:3857", "tileMatrices": []}"#)]
+    #[case(r#"{"crs": "EPSG:3857", "tileMatrices": [{"cellSize": 1, "pointOfOrigin": [0, 0]}]}"#)]
+    #[case(r#"{"crs": "EPSG:3857", "tileMatrices": [{"cellSize": 1, "cornerOfOrigin": "bottomLeft", "pointOfOrigin": [0, 0], "tileWidth": 256, "tileHeight": 256, "matrixWidth": 1, "matrixHeight": 1}]}"#)]
+    #[case(r#"{"crs": "EPSG:3857", "tileMatrices": [{"cellSize": 1, "pointOfOrigin": [0, 0], "tileWidth": 512, "tileHeight": 256, "matrixWidth": 1, "matrixHeight": 1}]}"#)]
+    fn parse_json_invalid(#[case] contents: &str) {
+        assert!(TileMatrixSet::parse_json(contents).is_err());
+    }
+
+    /// Create JSON tile matrix set with WebMercatorQuad matrices for zoom
+    /// levels 0 to maxzoom
+    fn web_mercator_json(id: &str, crs: &str, maxzoom: u8, tile_size: u32, origin: f64) -> String {
+        let matrices: Vec<String> = (0..=maxzoom)
+            .map(|zoom| {
+                format!(
+                    r#"{{"id": "{zoom}", "cellSize": {}, "pointOfOrigin": [{}, {}], "tileWidth": {tile_size}, "tileHeight": {tile_size}, "matrixWidth": {}, "matrixHeight": {}}}"#,
+                    CE / (tile_size as f64 * (1u64 << zoom) as f64),
+                    -origin,
+                    origin,
+                    1u32 << zoom,
+                    1u32 << zoom,
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"id": "{id}", "crs": "{crs}", "tileMatrices": [{}]}}"#,
+            matrices.join(",")
+        )
+    }
+
+    #[rstest]
+    #[case(
+        "WebMercatorQuad",
+        "http://www.opengis.net/def/crs/EPSG/0/3857",
+        24,
+        256,
+        ORIGIN,
+        true
+    )]
+    #[case("Custom", "EPSG:3857", 10, 256, ORIGIN, true)]
+    #[case("Custom", "EPSG:3857", 24, 512, ORIGIN, true)]
+    #[case("WebMercatorQuad", "EPSG:3395", 24, 256, ORIGIN, false)]
+    #[case("WebMercatorQuad", "EPSG:3857", 25, 256, ORIGIN, false)]
+    #[case("WebMercatorQuad", "EPSG:3857", 24, 256, 1e7, false)]
+    fn is_web_mercator(
+        #[case] id: &str,
+        #[case] crs: &str,
+        #[case] maxzoom: u8,
+        #[case] tile_size: u32,
+        #[case] origin: f64,
+        #[case] expected: bool,
+    ) {
+        let contents = web_mercator_json(id, crs, maxzoom, tile_size, origin);
+        let tms = TileMatrixSet::parse_json(&contents).unwrap();
+        assert_eq!(tms.is_web_mercator(), expected);
+    }
+
+    #[rstest]
+    #[case(TileMatrixSet::web_mercator_quad(), Some(CE))]
+    #[case(TileMatrixSet::world_crs84_quad(), Some(360.))]
+    #[case(TileMatrixSet::parse_json(&web_mercator_json("Custom", "EPSG:3395", 2, 256, ORIGIN)).unwrap(), None)]
+    fn world_width(#[case] tms: TileMatrixSet, #[case] expected: Option<f64>) {
+        assert_eq!(tms.world_width(), expected);
+    }
+
+    #[test]
+    fn is_web_mercator_builtin() {
+        assert!(TileMatrixSet::web_mercator_quad().is_web_mercator());
+        assert!(!TileMatrixSet::world_crs84_quad().is_web_mercator());
+    }
+
+    #[rstest]
+    #[case("http://www.opengis.net/def/crs/EPSG/0/3857", "EPSG:3857")]
+    #[case("http://www.opengis.net/def/crs/OGC/1.3/CRS84", "OGC:CRS84")]
+    #[case("EPSG:4326", "EPSG:4326")]
+    fn crs_from_uri(#[case] uri: &str, #[case] expected: &str) {
+        assert_eq!(super::crs_from_uri(uri), expected);
+    }
+}
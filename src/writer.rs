@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::tileid::TileID;
+
+/// Tileset output that rendered tiles are written to; tiles are written
+/// concurrently by all workers
+pub trait TileWriter: Sync {
+    /// Set tileset metadata from name / value pairs
+    fn set_metadata(&self, metadata: &[(&str, &str)]) -> Result<(), Box<dyn Error>>;
+
+    /// Write tile data for tile
+    fn write_tile(&self, tile_id: &TileID, tile_data: &[u8]) -> Result<()>;
+
+    /// Finish writing the tileset after all tiles have been written
+    fn finish(&self) -> Result<()>;
+}
+
+/// Type of tileset output, determined from the extension of the output path or
+/// a trailing path separator for directories
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputType {
+    MBTiles,
+    PMTiles,
+    GeoPackage,
+    /// Directory of {z}/{x}/{y} tile files; used if the path ends with a path
+    /// separator, e.g., "tiles/"
+    Directory,
+}
+
+impl OutputType {
+    pub fn from_path(path: &Path) -> OutputType {
+        // directories must be explicit, since paths without an extension, or
+        // with any other extension, are MBTiles
+        let path_str = path.as_os_str().to_string_lossy();
+        if path_str.ends_with('/') || path_str.ends_with(std::path::MAIN_SEPARATOR) {
+            return OutputType::Directory;
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pmtiles") => OutputType::PMTiles,
+            Some("gpkg") => OutputType::GeoPackage,
+            _ => OutputType::MBTiles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("tiles.mbtiles", OutputType::MBTiles)]
+    #[case("/tmp/tiles.pmtiles", OutputType::PMTiles)]
+    #[case("tiles.gpkg", OutputType::GeoPackage)]
+    #[case("tiles", OutputType::MBTiles)]
+    #[case("tiles.v2", OutputType::MBTiles)]
+    #[case("/tmp/tiles/", OutputType::Directory)]
+    #[case("tiles.v2/", OutputType::Directory)]
+    fn output_type(#[case] path: &str, #[case] expected: OutputType) {
+        assert_eq!(OutputType::from_path(Path::new(path)), expected);
+    }
+}